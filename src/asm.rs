@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::isa::Instruction;
+use super::romfile::ROM_SIZE;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    InvalidOperands(String),
+    InvalidNumber(String),
+    OutOfRange(u32, u32),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    TooLong,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmErrorKind::UnknownMnemonic(name) => write!(f, "unknown mnemonic \"{}\"", name),
+            AsmErrorKind::InvalidOperands(text) => write!(f, "invalid operands \"{}\"", text),
+            AsmErrorKind::InvalidNumber(text) => write!(f, "invalid number \"{}\"", text),
+            AsmErrorKind::OutOfRange(value, max) => {
+                write!(f, "{} is out of range (0-{})", value, max)
+            }
+            AsmErrorKind::UndefinedLabel(name) => write!(f, "undefined label \"{}\"", name),
+            AsmErrorKind::DuplicateLabel(name) => write!(f, "label \"{}\" defined twice", name),
+            AsmErrorKind::TooLong => {
+                write!(f, "program is longer than {} instructions", ROM_SIZE)
+            }
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+/// Assembles TD4 source into a ROM image.
+///
+/// Each line holds an optional `label:`, an optional instruction and an
+/// optional comment starting with `;` or `//`. Immediates may be decimal,
+/// `0x` hex or `0b` binary; `OUT` also takes four bare binary digits as
+/// printed by the disassembler. `DB <byte>` emits a raw byte.
+pub fn assemble(source: &str) -> Result<[u8; ROM_SIZE], Vec<AsmError>> {
+    let bytes = assemble_bytes(source)?;
+    let mut rom = [0; ROM_SIZE];
+    rom[..bytes.len()].copy_from_slice(&bytes);
+    Ok(rom)
+}
+
+/// Like [`assemble`], but returns only as many bytes as there are
+/// statements.
+pub fn assemble_bytes(source: &str) -> Result<Vec<u8>, Vec<AsmError>> {
+    let mut errors = Vec::new();
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line_no = i + 1;
+        let mut text = strip_comment(line).trim();
+
+        if let Some(pos) = text.find(':') {
+            let label = text[..pos].trim();
+            if is_identifier(label) {
+                if labels
+                    .insert(label.to_uppercase(), statements.len() as u32)
+                    .is_some()
+                {
+                    errors.push(AsmError {
+                        line: line_no,
+                        kind: AsmErrorKind::DuplicateLabel(label.to_string()),
+                    });
+                }
+                text = text[pos + 1..].trim();
+            }
+        }
+
+        if !text.is_empty() {
+            if statements.len() == ROM_SIZE {
+                errors.push(AsmError {
+                    line: line_no,
+                    kind: AsmErrorKind::TooLong,
+                });
+            }
+            statements.push((line_no, text));
+        }
+    }
+
+    let mut rom = vec![0; statements.len().min(ROM_SIZE)];
+    for (addr, (line_no, text)) in statements.into_iter().enumerate().take(ROM_SIZE) {
+        match encode_statement(text, &labels) {
+            Ok(byte) => rom[addr] = byte,
+            Err(kind) => errors.push(AsmError {
+                line: line_no,
+                kind,
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(rom)
+    } else {
+        errors.sort_by_key(|err| err.line);
+        Err(errors)
+    }
+}
+
+/// Parses a single ROM byte typed as `0xB3`, `0b10110011`, eight bare
+/// binary digits or one instruction such as `MOV A,3`.
+pub fn parse_byte(text: &str) -> Result<u8, AsmErrorKind> {
+    let text = text.trim();
+    if text.len() == 8 && text.chars().all(|c| c == '0' || c == '1') {
+        return u8::from_str_radix(text, 2)
+            .map_err(|_| AsmErrorKind::InvalidNumber(text.to_string()));
+    }
+    if text.starts_with(|c: char| c.is_ascii_digit()) {
+        return immediate(text, &HashMap::new(), 0xFF);
+    }
+    encode_statement(text, &HashMap::new())
+}
+
+/// Produces source text that assembles back into `rom`.
+pub fn disassemble_rom(rom: &[u8]) -> String {
+    rom.iter()
+        .map(|&byte| match Instruction::decode(byte) {
+            // `MOV A,B` and the like ignore the low nibble, which would be
+            // lost by printing the mnemonic.
+            Some(inst) if inst.encode() == byte => inst.to_string(),
+            _ => format!("DB 0b{:08b}", byte),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn strip_comment(line: &str) -> &str {
+    let end = [line.find(';'), line.find("//")]
+        .iter()
+        .flatten()
+        .min()
+        .copied()
+        .unwrap_or(line.len());
+    &line[..end]
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn encode_statement(text: &str, labels: &HashMap<String, u32>) -> Result<u8, AsmErrorKind> {
+    let (mnemonic, rest) = match text.find(char::is_whitespace) {
+        Some(pos) => (&text[..pos], text[pos..].trim()),
+        None => (text, ""),
+    };
+    let operands = if rest.is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',').map(str::trim).collect::<Vec<_>>()
+    };
+    let upper = operands
+        .iter()
+        .map(|op| op.to_uppercase())
+        .collect::<Vec<_>>();
+    let upper = upper.iter().map(String::as_str).collect::<Vec<_>>();
+    let invalid = || AsmErrorKind::InvalidOperands(rest.to_string());
+    let im = |op: &str| immediate(op, labels, 0x0F);
+
+    let inst = match (mnemonic.to_uppercase().as_str(), upper.as_slice()) {
+        ("ADD", ["A", _]) => Instruction::AddA(im(operands[1])?),
+        ("ADD", ["B", _]) => Instruction::AddB(im(operands[1])?),
+        ("MOV", ["A", "B"]) => Instruction::MovAB,
+        ("MOV", ["B", "A"]) => Instruction::MovBA,
+        ("MOV", ["A", _]) => Instruction::MovA(im(operands[1])?),
+        ("MOV", ["B", _]) => Instruction::MovB(im(operands[1])?),
+        ("IN", ["A"]) => Instruction::InA,
+        ("IN", ["B"]) => Instruction::InB,
+        ("OUT", ["B"]) => Instruction::OutB,
+        ("OUT", [_]) => {
+            let op = operands[0];
+            if op.len() == 4 && op.chars().all(|c| c == '0' || c == '1') {
+                Instruction::Out(u8::from_str_radix(op, 2).map_err(|_| invalid())?)
+            } else {
+                Instruction::Out(im(op)?)
+            }
+        }
+        ("JNC", [_]) => Instruction::Jnc(im(operands[0])?),
+        ("JMP", [_]) => Instruction::Jmp(im(operands[0])?),
+        ("DB", [_]) => return immediate(operands[0], labels, 0xFF),
+        ("ADD", _) | ("MOV", _) | ("IN", _) | ("OUT", _) | ("JNC", _) | ("JMP", _) | ("DB", _) => {
+            return Err(invalid())
+        }
+        _ => return Err(AsmErrorKind::UnknownMnemonic(mnemonic.to_string())),
+    };
+    Ok(inst.encode())
+}
+
+fn immediate(text: &str, labels: &HashMap<String, u32>, max: u32) -> Result<u8, AsmErrorKind> {
+    let value = if is_identifier(text) {
+        *labels
+            .get(&text.to_uppercase())
+            .ok_or_else(|| AsmErrorKind::UndefinedLabel(text.to_string()))?
+    } else {
+        parse_number(text).ok_or_else(|| AsmErrorKind::InvalidNumber(text.to_string()))?
+    };
+    if value > max {
+        return Err(AsmErrorKind::OutOfRange(value, max));
+    }
+    Ok(value as u8)
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    let text = text.replace('_', "");
+    let lower = text.to_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u32::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assembles_every_mnemonic() {
+        let source = "ADD A,1\nMOV A,B\nIN A\nMOV A,3\nMOV B,A\nADD B,5\nIN B\nMOV B,7\n\
+                      OUT B\nOUT 1010\nJNC 11\nJMP 12\nmov a , b\nout b";
+        assert_eq!(
+            assemble_bytes(source),
+            Ok(vec![
+                0b0000_0001,
+                0b0001_0000,
+                0b0010_0000,
+                0b0011_0011,
+                0b0100_0000,
+                0b0101_0101,
+                0b0110_0000,
+                0b0111_0111,
+                0b1001_0000,
+                0b1011_1010,
+                0b1110_1011,
+                0b1111_1100,
+                0b0001_0000,
+                0b1001_0000,
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_immediates() {
+        assert_eq!(assemble_bytes("MOV A,0xF"), Ok(vec![0b0011_1111]));
+        assert_eq!(assemble_bytes("MOV A,0b0110"), Ok(vec![0b0011_0110]));
+        assert_eq!(assemble_bytes("MOV A,0b01_10"), Ok(vec![0b0011_0110]));
+        assert_eq!(assemble_bytes("OUT 9"), Ok(vec![0b1011_1001]));
+        assert_eq!(assemble_bytes("OUT 0011"), Ok(vec![0b1011_0011]));
+        assert_eq!(assemble_bytes("DB 0xB3"), Ok(vec![0xB3]));
+        assert_eq!(
+            assemble_bytes("start: OUT 1\nloop: JMP Loop ; comment\nJNC start // too"),
+            Ok(vec![0b1011_0001, 0b1111_0001, 0b1110_0000])
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let source =
+            "MOV A,1\n\nFOO A\nMOV A,16\nJMP nowhere\nMOV C,1\nADD A,x1\nx: OUT 1\nx: OUT 2";
+        let kinds = |errors: Vec<AsmError>| {
+            errors
+                .into_iter()
+                .map(|err| (err.line, err.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            assemble_bytes(source).map_err(kinds),
+            Err(vec![
+                (3, AsmErrorKind::UnknownMnemonic("FOO".to_string())),
+                (4, AsmErrorKind::OutOfRange(16, 15)),
+                (5, AsmErrorKind::UndefinedLabel("nowhere".to_string())),
+                (6, AsmErrorKind::InvalidOperands("C,1".to_string())),
+                (7, AsmErrorKind::UndefinedLabel("x1".to_string())),
+                (9, AsmErrorKind::DuplicateLabel("x".to_string())),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_programs_longer_than_the_rom() {
+        let source = vec!["OUT 1"; ROM_SIZE + 1].join("\n");
+        assert_eq!(
+            assemble(&source),
+            Err(vec![AsmError {
+                line: ROM_SIZE + 1,
+                kind: AsmErrorKind::TooLong,
+            }])
+        );
+    }
+
+    #[test]
+    fn disassembly_assembles_back_for_every_byte() {
+        for byte in 0..=255u8 {
+            let source = disassemble_rom(&[byte]);
+            assert_eq!(assemble_bytes(&source), Ok(vec![byte]), "{}", source);
+        }
+
+        let rom = [
+            0xB3, 0x80, 0x00, 0xFF, 0x12, 0xC7, 0x51, 0xE0, 0, 0, 0, 0, 0, 0, 0, 0xD0,
+        ];
+        assert_eq!(assemble(&disassemble_rom(&rom)), Ok(rom));
+    }
+
+    #[test]
+    fn parses_single_bytes() {
+        assert_eq!(parse_byte("0xB3"), Ok(0xB3));
+        assert_eq!(parse_byte("10110011"), Ok(0xB3));
+        assert_eq!(parse_byte(" OUT 0011 "), Ok(0xB3));
+        assert_eq!(parse_byte("256"), Err(AsmErrorKind::OutOfRange(256, 255)));
+    }
+}
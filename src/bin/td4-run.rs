@@ -0,0 +1,296 @@
+use std::fmt;
+use std::process;
+
+use td4::halt::HaltAction;
+use td4::machine::{Command, Machine, DEFAULT_PERIOD, MAX_PERIOD, MIN_PERIOD};
+use td4::romfile::{self, ROM_SIZE};
+use td4::stimulus::{self, parse_nibble, Stimulus};
+use td4::trace::Sample;
+use td4::vcd;
+
+const USAGE: &str = "\
+Usage: td4-run [OPTIONS] <PROGRAM>
+
+Runs a TD4 program without a window and prints the trace. The program
+format is chosen by extension as in the GUI (.bin, .memb, .memh, .rom, .hex, .c).
+
+Options:
+  -n, --cycles <N>          Maximum number of cycles to run (default: 100)
+  -i, --input <SCHEDULE>    Input port schedule as time:value pairs, e.g. 0:0b0001,20:3,500ms:0
+  -s, --stimulus <FILE>     Read the input port schedule from FILE
+  -p, --period <MS>         Clock period used for millisecond events, 100-1000 (default: 300)
+      --until-pc <ADDR>     Stop once the program counter reaches ADDR
+      --until-output <V>    Stop once the output port shows V
+      --until-loop          Stop at a JMP to its own address
+  -f, --format <FORMAT>     Output format: text, json or vcd (default: text)
+  -h, --help                Print this help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+    Vcd,
+}
+
+#[derive(Debug)]
+struct Options {
+    program: String,
+    cycles: u64,
+    input: Stimulus,
+    period: u64,
+    until_pc: Option<u8>,
+    until_output: Option<u8>,
+    until_loop: bool,
+    format: Format,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Halt {
+    CycleLimit,
+    Pc(u8),
+    Output(u8),
+    Loop(u8),
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Halt::CycleLimit => write!(f, "cycle limit"),
+            Halt::Pc(addr) => write!(f, "pc reached {}", addr),
+            Halt::Output(value) => write!(f, "output reached {}", value),
+            Halt::Loop(addr) => write!(f, "self jump at {}", addr),
+        }
+    }
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut program = None;
+    let mut options = Options {
+        program: String::new(),
+        cycles: 100,
+        input: Stimulus::default(),
+        period: DEFAULT_PERIOD,
+        until_pc: None,
+        until_output: None,
+        until_loop: false,
+        format: Format::Text,
+    };
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Err(String::new()),
+            "-n" | "--cycles" => {
+                let v = value()?;
+                options.cycles = v
+                    .parse()
+                    .map_err(|_| format!("invalid cycle count \"{}\"", v))?;
+            }
+            "-i" | "--input" => {
+                let v = value()?;
+                for (time, input) in Stimulus::parse_inline(&v)
+                    .map_err(|err| err.to_string())?
+                    .events()
+                {
+                    options.input.insert(*time, *input);
+                }
+            }
+            "-s" | "--stimulus" => {
+                let v = value()?;
+                for (time, input) in stimulus::load(&v)
+                    .map_err(|err| format!("cannot read {}: {}", v, err))?
+                    .events()
+                {
+                    options.input.insert(*time, *input);
+                }
+            }
+            "-p" | "--period" => {
+                let v = value()?;
+                options.period = v
+                    .parse()
+                    .ok()
+                    .filter(|ms| (MIN_PERIOD..=MAX_PERIOD).contains(ms))
+                    .ok_or_else(|| format!("invalid period \"{}\"", v))?;
+            }
+            "--until-pc" => {
+                let v = value()?;
+                options.until_pc =
+                    Some(parse_nibble(&v).ok_or_else(|| format!("invalid address \"{}\"", v))?);
+            }
+            "--until-output" => {
+                let v = value()?;
+                options.until_output =
+                    Some(parse_nibble(&v).ok_or_else(|| format!("invalid value \"{}\"", v))?);
+            }
+            "--until-loop" => options.until_loop = true,
+            "-f" | "--format" => {
+                let v = value()?;
+                options.format = match v.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "vcd" => Format::Vcd,
+                    _ => return Err(format!("unknown format \"{}\"", v)),
+                };
+            }
+            _ if arg.starts_with('-') => return Err(format!("unexpected argument \"{}\"", arg)),
+            _ if program.is_none() => program = Some(arg),
+            _ => return Err(format!("unexpected argument \"{}\"", arg)),
+        }
+    }
+
+    options.program = program.ok_or_else(|| String::from("missing program file"))?;
+    Ok(options)
+}
+
+/// Loads `rom` into a [`Machine`] set up like the GUI's.
+fn machine(rom: &[u8; ROM_SIZE], options: &Options) -> Machine {
+    let mut machine = Machine::default();
+    machine.apply(Command::LoadRom(*rom));
+    machine.apply(Command::SetPeriod(options.period));
+    machine.apply(Command::LoadStimulus(options.input.clone()));
+    machine.apply(Command::SetHaltAction(HaltAction::Ignore));
+    machine
+}
+
+/// Steps the machine until a stop condition holds. The machine's own trace
+/// only keeps the latest cycles, so every sample is copied out once the
+/// input for its cycle has been applied.
+fn run(machine: &mut Machine, options: &Options) -> (Vec<Sample>, Halt) {
+    let mut trace = Vec::new();
+    let halt = loop {
+        let cycle = machine.cycles();
+        if cycle == options.cycles {
+            break Halt::CycleLimit;
+        }
+        let cpu = machine.cpu();
+        let pc = cpu.reg.pc;
+        if options.until_loop && cpu.prg.mem[pc as usize] == 0xF0 | pc {
+            break Halt::Loop(pc);
+        }
+
+        machine.apply(Command::Step);
+        trace.extend(machine.trace().get(cycle).copied());
+
+        let cpu = machine.cpu();
+        if options.until_pc == Some(cpu.reg.pc) {
+            break Halt::Pc(cpu.reg.pc);
+        }
+        if options.until_output == Some(cpu.port.output) {
+            break Halt::Output(cpu.port.output);
+        }
+    };
+    trace.extend(machine.trace().last().copied());
+    (trace, halt)
+}
+
+fn print_text(trace: &[Sample], halt: Halt) {
+    println!("cycle  pc  a  b  c  in    out");
+    for c in trace {
+        println!(
+            "{:5}  {:2}  {:X}  {:X}  {}  {:04b}  {:04b}",
+            c.cycle, c.pc, c.a, c.b, c.carry as u8, c.input, c.output
+        );
+    }
+    println!("halted: {}", halt);
+}
+
+fn print_json(trace: &[Sample], halt: Halt) {
+    let cycles = trace
+        .iter()
+        .map(|c| {
+            format!(
+                "{{\"cycle\":{},\"pc\":{},\"a\":{},\"b\":{},\"carry\":{},\"input\":{},\"output\":{}}}",
+                c.cycle, c.pc, c.a, c.b, c.carry, c.input, c.output
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n    ");
+    println!(
+        "{{\n  \"halt\": \"{}\",\n  \"trace\": [\n    {}\n  ]\n}}",
+        halt, cycles
+    );
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) if err.is_empty() => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let rom = match romfile::load(&options.program) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("error: cannot open {}: {}", options.program, err);
+            process::exit(1);
+        }
+    };
+
+    let (trace, halt) = run(&mut machine(&rom, &options), &options);
+    match options.format {
+        Format::Text => print_text(&trace, halt),
+        Format::Json => print_json(&trace, halt),
+        Format::Vcd => print!("{}", vcd::format(&trace, options.period)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Options {
+        parse_args(args.iter().map(|arg| arg.to_string())).unwrap()
+    }
+
+    /// IN A / MOV B,A / OUT B / JMP 0
+    fn echo() -> [u8; ROM_SIZE] {
+        let mut rom = [0; ROM_SIZE];
+        rom[..4].copy_from_slice(&[0b0010_0000, 0b0100_0000, 0b1001_0000, 0b1111_0000]);
+        rom
+    }
+
+    #[test]
+    fn trace_matches_the_gui() {
+        let options = options(&[
+            "-n",
+            "12",
+            "-p",
+            "200",
+            "-i",
+            "0:3,2:5,1000ms:9",
+            "echo.bin",
+        ]);
+        let (trace, halt) = run(&mut machine(&echo(), &options), &options);
+        assert_eq!(halt, Halt::CycleLimit);
+
+        // The GUI runs the same machine from the clock's ticks.
+        let mut gui = machine(&echo(), &options);
+        gui.apply(Command::Run);
+        for _ in 0..options.cycles {
+            gui.apply(Command::Tick);
+        }
+        assert_eq!(trace, gui.trace().iter().copied().collect::<Vec<_>>());
+        assert_eq!(trace.len(), 13);
+        assert_eq!(trace[0].input, 3);
+        assert_eq!(trace[2].input, 5);
+        assert_eq!(trace[5].input, 9);
+    }
+
+    #[test]
+    fn stops_at_the_requested_condition() {
+        let options = options(&["-i", "0:6", "--until-output", "6", "echo.bin"]);
+        let (trace, halt) = run(&mut machine(&echo(), &options), &options);
+        assert_eq!(halt, Halt::Output(6));
+        assert_eq!(trace.last().map(|s| (s.cycle, s.output)), Some((3, 6)));
+    }
+}
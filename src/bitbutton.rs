@@ -1,30 +1,57 @@
-use iced::{button, Button, Row, Text};
+use iced::{button, text_input, Button, Column, Element, Length, Row, Text, TextInput};
 
+use super::momentary::{self, Momentary};
 use super::style;
-use super::td4_gui::Message;
+use super::td4_gui::{scaled, Message};
 
 #[derive(Debug, Default, Clone)]
 pub struct InputHalfByte {
     pub bit_state: [button::State; 4],
+    pub hold_state: [momentary::State; 4],
+    pub mode_state: [button::State; 4],
+    /// Bits set here act as push buttons and are only on while held.
+    pub momentary: [bool; 4],
 }
 
 impl InputHalfByte {
-    pub fn crate_layout(&mut self, value: &u8) -> Row<Message> {
-        self.buttons(value)
+    pub fn crate_layout(&mut self, value: &u8, scale: f32) -> Row<Message> {
+        self.buttons(value, scale)
             .into_iter()
             .fold(Row::new().spacing(1), |row, button| row.push(button))
     }
 
-    fn buttons(&mut self, value: &u8) -> Vec<Button<Message>> {
+    fn buttons(&mut self, value: &u8, scale: f32) -> Vec<Column<Message>> {
+        let momentary = self.momentary;
         self.bit_state
             .iter_mut()
+            .zip(self.hold_state.iter_mut())
+            .zip(self.mode_state.iter_mut())
             .enumerate()
-            .map(|(i, state)| {
+            .map(|(i, ((state, hold), mode))| {
                 let bit = value & (0x01 << i) != 0;
-                let btn = Button::new(state, bit2text(bit))
+                let btn = Button::new(state, bit2text(bit, scale))
+                    .padding(scaled(5.0, scale))
                     .on_press(Message::InputEdit(i as u8, bit))
                     .style(bit2style(bit));
-                btn
+                let btn: Element<Message> = if momentary[i] {
+                    Momentary::new(
+                        hold,
+                        btn,
+                        Message::InputPress(i as u8),
+                        Message::InputRelease(i as u8),
+                    )
+                    .into()
+                } else {
+                    btn.into()
+                };
+                let mode = Button::new(
+                    mode,
+                    Text::new(if momentary[i] { "M" } else { "L" }).size(scaled(14.0, scale)),
+                )
+                .padding(scaled(5.0, scale))
+                .on_press(Message::InputModeToggled(i as u8))
+                .style(style::Theme::Dark);
+                Column::new().spacing(1).push(btn).push(mode)
             })
             .rev()
             .collect::<_>()
@@ -34,22 +61,43 @@ impl InputHalfByte {
 #[derive(Debug, Default, Clone)]
 pub struct RomByte {
     pub bit_state: [button::State; 8],
+    pub entry: text_input::State,
+    /// Text typed into `entry` that has not been committed yet.
+    pub text: String,
 }
 
 impl RomByte {
-    pub fn create_layout(&mut self, addr: usize, value: &u8) -> Row<Message> {
-        self.buttons(addr, value)
+    pub fn create_layout(&mut self, addr: usize, value: &u8, scale: f32) -> Row<Message> {
+        let row = Self::buttons(&mut self.bit_state, addr, *value, scale)
             .into_iter()
-            .fold(Row::new().spacing(1), |row, button| row.push(button))
+            .fold(Row::new().spacing(1), |row, button| row.push(button));
+        row.push(
+            TextInput::new(
+                &mut self.entry,
+                &format!("0x{:02X}", value),
+                &self.text,
+                move |text| Message::RomEntryChanged(addr, text),
+            )
+            .on_submit(Message::RomEntrySubmit(addr))
+            .width(Length::from(scaled(90.0, scale)))
+            .padding(scaled(5.0, scale))
+            .size(scaled(16.0, scale)),
+        )
     }
 
-    fn buttons(&mut self, addr: usize, value: &u8) -> Vec<Button<Message>> {
-        self.bit_state
+    fn buttons(
+        bit_state: &mut [button::State; 8],
+        addr: usize,
+        value: u8,
+        scale: f32,
+    ) -> Vec<Button<Message>> {
+        bit_state
             .iter_mut()
             .enumerate()
             .map(|(i, state)| {
                 let bit = value & (0x01 << i) != 0;
-                let btn = Button::new(state, bit2text(bit))
+                let btn = Button::new(state, bit2text(bit, scale))
+                    .padding(scaled(5.0, scale))
                     .on_press(Message::RomEdit(addr, i as u8, bit))
                     .style(bit2style(bit));
                 btn
@@ -65,27 +113,39 @@ pub struct RomTable {
 }
 
 impl RomTable {
-    pub fn create_layout(&mut self, prg: &td4_emu::mem::Mem) -> Vec<Row<Message>> {
-        self.table = vec![RomByte::default(); prg.mem.len()];
+    pub fn create_layout(&mut self, prg: &td4_emu::mem::Mem, scale: f32) -> Vec<Row<Message>> {
+        if self.table.len() != prg.mem.len() {
+            self.table = vec![RomByte::default(); prg.mem.len()];
+        }
 
         let buttons = self
             .table
             .iter_mut()
             .enumerate()
-            .map(|(i, rombyte)| rombyte.create_layout(i, &prg.mem[i]))
+            .map(|(i, rombyte)| rombyte.create_layout(i, &prg.mem[i], scale))
             .collect::<Vec<_>>();
         buttons
     }
-}
 
-fn bit2text(bit: bool) -> Text {
-    if bit {
-        Text::new("1")
-    } else {
-        Text::new("0")
+    /// Address of the row whose text entry has focus.
+    pub fn focused(&self) -> Option<usize> {
+        self.table.iter().position(|row| row.entry.is_focused())
+    }
+
+    pub fn focus(&mut self, addr: usize) {
+        for row in self.table.iter_mut() {
+            row.entry.unfocus();
+        }
+        if let Some(row) = self.table.get_mut(addr) {
+            row.entry.focus();
+        }
     }
 }
 
+fn bit2text(bit: bool, scale: f32) -> Text {
+    if bit { Text::new("1") } else { Text::new("0") }.size(scaled(20.0, scale))
+}
+
 fn bit2style(bit: bool) -> style::Theme {
     if bit {
         style::Theme::Light
@@ -0,0 +1,186 @@
+use std::fmt;
+
+use td4::machine::{DEFAULT_PERIOD, MAX_PERIOD, MIN_PERIOD};
+use td4::stimulus::parse_nibble;
+
+pub const USAGE: &str = "\
+Usage: td4-gui [OPTIONS] [PROGRAM]
+
+Arguments:
+  [PROGRAM]              Program file to load (default: last used, or prg.bin)
+
+Options:
+  -p, --period <MS>      Clock period in milliseconds, 100-1000 (default: 300)
+  -i, --input <VALUE>    Initial input port value, 0-15 (0b/0x prefixes allowed)
+  -r, --run              Start running immediately
+  -s, --size <WxH>       Window size in pixels (default: 1120x720)
+  -t, --stimulus <FILE>  Input port stimulus file with time:value events
+  -k, --keymap <FILE>    Key bindings file with `key = action` lines
+  -h, --help             Print this help
+
+Period, window size and program default to the values saved in the settings
+file, which the options override.";
+
+#[derive(Debug, Clone)]
+pub struct Flags {
+    pub program: String,
+    pub period: u64,
+    pub input: u8,
+    pub run: bool,
+    pub size: (u32, u32),
+    pub stimulus: Option<String>,
+    pub keymap: Option<String>,
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Flags {
+            program: "prg.bin".to_string(),
+            period: DEFAULT_PERIOD,
+            input: 0,
+            run: false,
+            size: (1120, 720),
+            stimulus: None,
+            keymap: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    Help,
+    MissingValue(String),
+    InvalidValue(String, String),
+    Unexpected(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{}", USAGE),
+            CliError::MissingValue(opt) => write!(f, "missing value for {}", opt),
+            CliError::InvalidValue(opt, value) => {
+                write!(f, "invalid value \"{}\" for {}", value, opt)
+            }
+            CliError::Unexpected(arg) => write!(f, "unexpected argument \"{}\"", arg),
+        }
+    }
+}
+
+impl Flags {
+    /// Parses `args` on top of `defaults`.
+    pub fn parse<I: IntoIterator<Item = String>>(
+        defaults: Flags,
+        args: I,
+    ) -> Result<Flags, CliError> {
+        let mut flags = defaults;
+        let mut program = None;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Err(CliError::Help),
+                "-r" | "--run" => flags.run = true,
+                "-p" | "--period" => {
+                    let value = args.next().ok_or(CliError::MissingValue(arg.clone()))?;
+                    flags.period = value
+                        .parse()
+                        .ok()
+                        .filter(|ms| (MIN_PERIOD..=MAX_PERIOD).contains(ms))
+                        .ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "-i" | "--input" => {
+                    let value = args.next().ok_or(CliError::MissingValue(arg.clone()))?;
+                    flags.input = parse_nibble(&value).ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "-s" | "--size" => {
+                    let value = args.next().ok_or(CliError::MissingValue(arg.clone()))?;
+                    flags.size = parse_size(&value).ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "-t" | "--stimulus" => {
+                    let value = args.next().ok_or(CliError::MissingValue(arg.clone()))?;
+                    flags.stimulus = Some(value);
+                }
+                "-k" | "--keymap" => {
+                    let value = args.next().ok_or(CliError::MissingValue(arg.clone()))?;
+                    flags.keymap = Some(value);
+                }
+                _ if arg.starts_with('-') => return Err(CliError::Unexpected(arg)),
+                _ if program.is_none() => program = Some(arg),
+                _ => return Err(CliError::Unexpected(arg)),
+            }
+        }
+
+        if let Some(program) = program {
+            flags.program = program;
+        }
+        Ok(flags)
+    }
+}
+
+pub fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let mut dims = text.splitn(2, 'x');
+    let width = dims.next()?.parse().ok()?;
+    let height = dims.next()?.parse().ok()?;
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Flags, CliError> {
+        Flags::parse(Flags::default(), args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options_over_defaults() {
+        let flags = parse(&[
+            "-p", "500", "--input", "0b0101", "-r", "-s", "800x600", "a.hex",
+        ])
+        .unwrap();
+        assert_eq!(flags.program, "a.hex");
+        assert_eq!(flags.period, 500);
+        assert_eq!(flags.input, 5);
+        assert!(flags.run);
+        assert_eq!(flags.size, (800, 600));
+
+        let defaults = Flags {
+            period: 700,
+            program: String::from("last.bin"),
+            ..Flags::default()
+        };
+        let flags =
+            Flags::parse(defaults, vec![String::from("-t"), String::from("s.txt")]).unwrap();
+        assert_eq!(flags.period, 700);
+        assert_eq!(flags.program, "last.bin");
+        assert_eq!(flags.stimulus.as_deref(), Some("s.txt"));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(matches!(
+            parse(&["-p", "50"]),
+            Err(CliError::InvalidValue(..))
+        ));
+        assert!(matches!(
+            parse(&["-p", "1001"]),
+            Err(CliError::InvalidValue(..))
+        ));
+        assert!(matches!(
+            parse(&["-i", "16"]),
+            Err(CliError::InvalidValue(..))
+        ));
+        assert!(matches!(
+            parse(&["-s", "800"]),
+            Err(CliError::InvalidValue(..))
+        ));
+        assert!(matches!(parse(&["-k"]), Err(CliError::MissingValue(_))));
+        assert!(matches!(parse(&["--fast"]), Err(CliError::Unexpected(_))));
+        assert!(matches!(
+            parse(&["a.bin", "b.bin"]),
+            Err(CliError::Unexpected(_))
+        ));
+        assert!(matches!(parse(&["-h"]), Err(CliError::Help)));
+    }
+}
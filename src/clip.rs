@@ -0,0 +1,125 @@
+use std::fmt;
+
+use super::asm;
+use super::romfile;
+
+/// Text formats for copying ROM contents to the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipFormat {
+    #[default]
+    Assembly,
+    Binary,
+    Hex,
+}
+
+impl ClipFormat {
+    pub const ALL: [ClipFormat; 3] = [ClipFormat::Assembly, ClipFormat::Binary, ClipFormat::Hex];
+
+    pub fn format(self, bytes: &[u8]) -> String {
+        match self {
+            ClipFormat::Assembly => asm::disassemble_rom(bytes),
+            ClipFormat::Binary => romfile::format(bytes),
+            ClipFormat::Hex => bytes
+                .iter()
+                .map(|b| format!("0x{:02X}", b))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+impl fmt::Display for ClipFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipFormat::Assembly => write!(f, "Assembly"),
+            ClipFormat::Binary => write!(f, "Binary"),
+            ClipFormat::Hex => write!(f, "Hex"),
+        }
+    }
+}
+
+fn is_binary_byte(word: &str) -> bool {
+    word.len() == 8 && word.chars().all(|c| c == '0' || c == '1')
+}
+
+fn hex_byte(word: &str) -> Option<u8> {
+    let digits = word
+        .strip_prefix("0x")
+        .or_else(|| word.strip_prefix("0X"))
+        .unwrap_or(word);
+    if digits.is_empty() || digits.len() > 2 {
+        return None;
+    }
+    u8::from_str_radix(digits, 16).ok()
+}
+
+/// Parses pasted text in any [`ClipFormat`]: binary lines, assembly, or
+/// hex bytes separated by spaces or commas.
+pub fn parse(text: &str) -> Result<Vec<u8>, String> {
+    let words = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        return Err(String::from("nothing to paste"));
+    }
+
+    // Hex is tried last since a line such as `DB 12` would also pass as
+    // hex; copied hex has `0x` prefixes and never assembles.
+    let bytes = if words.iter().all(|w| is_binary_byte(w)) {
+        words
+            .iter()
+            .map(|w| u8::from_str_radix(w, 2).unwrap_or(0))
+            .collect()
+    } else {
+        match asm::assemble_bytes(text) {
+            Ok(bytes) => bytes,
+            Err(errors) => words
+                .iter()
+                .map(|w| hex_byte(w))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| errors[0].to_string())?,
+        }
+    };
+
+    if bytes.len() > romfile::ROM_SIZE {
+        return Err(format!(
+            "{} bytes found, but the ROM holds only {}",
+            bytes.len(),
+            romfile::ROM_SIZE
+        ));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_format_pastes_back() {
+        let roms: [&[u8]; 3] = [
+            &[0xDB, 0x12],
+            &[0x12],
+            &[0xDB, 0x12, 0x00, 0xB3, 0x80, 0x11, 0xFF, 0x0A],
+        ];
+        for bytes in roms.iter() {
+            for format in ClipFormat::ALL.iter() {
+                let text = format.format(bytes);
+                assert_eq!(parse(&text), Ok(bytes.to_vec()), "{}: {}", format, text);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_loose_text() {
+        assert_eq!(parse("0xb3, 1, ff"), Ok(vec![0xB3, 0x01, 0xFF]));
+        assert_eq!(parse("MOV A,3\nOUT 0101"), Ok(vec![0x33, 0xB5]));
+        assert_eq!(parse("DB 0xB3"), Ok(vec![0xB3]));
+        assert_eq!(parse("DB 12"), Ok(vec![12]));
+        assert_eq!(parse("DB 12 34"), Ok(vec![0xDB, 0x12, 0x34]));
+        assert!(parse(" \n").is_err());
+        assert!(parse("MOV C,1").is_err());
+        assert!(parse(&vec!["00"; 17].join(" ")).is_err());
+    }
+}
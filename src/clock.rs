@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const RATE_WINDOW: usize = 16;
+
+/// Tick interval in turbo mode, roughly one frame.
+pub const TURBO_INTERVAL: u64 = 16;
+/// Cycles executed per turbo tick.
+pub const TURBO_BATCH: u64 = 1000;
+
+/// Clock sources of the TD4 kit's selector switch, plus the adjustable
+/// period of the slider and an unthrottled turbo mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockSource {
+    Hz1,
+    Hz10,
+    Manual,
+    #[default]
+    Custom,
+    Turbo,
+}
+
+impl ClockSource {
+    pub const ALL: [ClockSource; 5] = [
+        ClockSource::Hz1,
+        ClockSource::Hz10,
+        ClockSource::Manual,
+        ClockSource::Custom,
+        ClockSource::Turbo,
+    ];
+
+    /// Nominal clock period in milliseconds; `None` when the clock is not
+    /// periodic.
+    pub fn period(self, custom: u64) -> Option<u64> {
+        match self {
+            ClockSource::Hz1 => Some(1000),
+            ClockSource::Hz10 => Some(100),
+            ClockSource::Custom => Some(custom),
+            ClockSource::Manual | ClockSource::Turbo => None,
+        }
+    }
+
+    /// Milliseconds between ticks while running; `None` for the manual clock.
+    pub fn interval(self, custom: u64) -> Option<u64> {
+        match self {
+            ClockSource::Turbo => Some(TURBO_INTERVAL),
+            _ => self.period(custom),
+        }
+    }
+
+    /// Number of cycles executed per tick.
+    pub fn batch(self) -> u64 {
+        match self {
+            ClockSource::Turbo => TURBO_BATCH,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for ClockSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ClockSource::Hz1 => "1 Hz",
+            ClockSource::Hz10 => "10 Hz",
+            ClockSource::Manual => "Manual",
+            ClockSource::Custom => "Custom",
+            ClockSource::Turbo => "Turbo",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Measures the real clock rate from the wall-clock times of recent ticks
+/// and the number of cycles each of them executed.
+#[derive(Debug, Default)]
+pub struct RateMeter {
+    ticks: VecDeque<(Instant, u64)>,
+}
+
+impl RateMeter {
+    pub fn tick(&mut self, now: Instant, cycles: u64) {
+        if self.ticks.len() == RATE_WINDOW {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back((now, cycles));
+    }
+
+    pub fn clear(&mut self) {
+        self.ticks.clear();
+    }
+
+    /// Average cycles per second over the window, or `None` until two
+    /// ticks were seen. The cycles of the first tick ran before the window
+    /// starts and are not counted.
+    pub fn hz(&self) -> Option<f64> {
+        let (first, _) = self.ticks.front()?;
+        let (last, _) = self.ticks.back()?;
+        let span = last.duration_since(*first);
+        if span == Duration::from_secs(0) {
+            return None;
+        }
+        let cycles: u64 = self.ticks.iter().skip(1).map(|&(_, cycles)| cycles).sum();
+        Some(cycles as f64 / span.as_secs_f64())
+    }
+}
+
+/// Clock rate produced by a period given in milliseconds.
+pub fn period_hz(period: u64) -> f64 {
+    1000.0 / period as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_counts_cycles_per_tick() {
+        let start = Instant::now();
+        let mut meter = RateMeter::default();
+        meter.tick(start, TURBO_BATCH);
+        assert_eq!(meter.hz(), None);
+
+        for i in 1..=4 {
+            meter.tick(start + Duration::from_millis(16 * i), TURBO_BATCH);
+        }
+        let hz = meter.hz().unwrap();
+        assert!((hz - 62_500.0).abs() < 1.0, "{}", hz);
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use td4::halt::HaltAction;
+use td4::machine::{MAX_PERIOD, MIN_PERIOD};
+
+use super::cli;
+use super::keymap::KeyMap;
+use super::style::Theme;
+
+/// User preferences kept between sessions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub theme: Theme,
+    pub period: u64,
+    pub halt: HaltAction,
+    pub size: (u32, u32),
+    /// Program opened or saved last.
+    pub program: Option<String>,
+    pub keymap: KeyMap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let flags = cli::Flags::default();
+        Config {
+            theme: Theme::default(),
+            period: flags.period,
+            halt: HaltAction::default(),
+            size: flags.size,
+            program: None,
+            keymap: KeyMap::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Parses `name = value` lines followed by an optional `[keys]` section
+    /// in the key bindings file format.
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut keys = false;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let trimmed = line.trim();
+            if trimmed == "[keys]" {
+                keys = true;
+                continue;
+            }
+            if keys {
+                config.keymap.parse_line(line_no, line)?;
+                continue;
+            }
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || format!("line {}: invalid setting \"{}\"", line_no, trimmed);
+            let mut parts = trimmed.splitn(2, '=').map(str::trim);
+            let name = parts.next().unwrap_or("");
+            let value = parts.next().ok_or_else(invalid)?;
+            match name {
+                "theme" => {
+                    config.theme = Theme::ALL
+                        .iter()
+                        .copied()
+                        .find(|theme| theme.to_string().eq_ignore_ascii_case(value))
+                        .ok_or_else(invalid)?;
+                }
+                "period" => {
+                    config.period = value
+                        .parse()
+                        .ok()
+                        .filter(|ms| (MIN_PERIOD..=MAX_PERIOD).contains(ms))
+                        .ok_or_else(invalid)?;
+                }
+                "halt" => {
+                    config.halt = HaltAction::ALL
+                        .iter()
+                        .copied()
+                        .find(|halt| halt.to_string().eq_ignore_ascii_case(value))
+                        .ok_or_else(invalid)?;
+                }
+                "size" => config.size = cli::parse_size(value).ok_or_else(invalid)?,
+                "program" => config.program = Some(value.to_string()),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn format(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "theme = {}", self.theme.to_string().to_lowercase());
+        let _ = writeln!(out, "period = {}", self.period);
+        let _ = writeln!(out, "halt = {}", self.halt.to_string().to_lowercase());
+        let _ = writeln!(out, "size = {}x{}", self.size.0, self.size.1);
+        if let Some(program) = &self.program {
+            let _ = writeln!(out, "program = {}", program);
+        }
+        out.push_str("\n[keys]\n");
+        out.push_str(&self.keymap.format());
+        out
+    }
+
+    /// Command line defaults taken from these preferences.
+    pub fn flags(&self) -> cli::Flags {
+        let mut flags = cli::Flags {
+            period: self.period,
+            size: self.size,
+            ..cli::Flags::default()
+        };
+        if let Some(program) = &self.program {
+            flags.program = program.clone();
+        }
+        flags
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, self.format()).map_err(|err| err.to_string())
+    }
+}
+
+/// Where the preferences are stored: `td4-gui/settings.conf` under
+/// `$XDG_CONFIG_HOME`, `~/.config` or `%APPDATA%`.
+pub fn path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(dir.join("td4-gui").join("settings.conf"))
+}
+
+/// Reads the preferences, falling back to the defaults when there is no
+/// settings file yet.
+pub fn load(path: &Path) -> Result<Config, String> {
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    Config::parse(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced_native::keyboard::KeyCode;
+
+    use crate::keymap::Action;
+
+    #[test]
+    fn format_parses_back() {
+        let mut config = Config {
+            theme: Theme::Dark,
+            period: 450,
+            halt: HaltAction::Warn,
+            size: (800, 600),
+            program: Some(String::from("programs/blink.hex")),
+            keymap: KeyMap::default(),
+        };
+        config.keymap.bind(KeyCode::Left, Action::ToggleInput(3));
+        let text = config.format();
+        assert!(text.contains("\n[keys]\n"));
+        assert_eq!(Config::parse(&text), Ok(config));
+        assert_eq!(Config::parse(""), Ok(Config::default()));
+    }
+
+    #[test]
+    fn parse_rejects_bad_values() {
+        assert_eq!(
+            Config::parse("theme = dark\ntheme = neon"),
+            Err(String::from("line 2: invalid setting \"theme = neon\""))
+        );
+        assert!(Config::parse("period = 50").is_err());
+        assert!(Config::parse("period = fast").is_err());
+        assert!(Config::parse("halt = pause").is_err());
+        assert!(Config::parse("size = 800").is_err());
+        assert!(Config::parse("colour = red").is_err());
+        assert!(Config::parse("[keys]\nSpace = jump").is_err());
+    }
+}
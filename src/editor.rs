@@ -0,0 +1,154 @@
+use iced::{
+    button, scrollable, text_input, Align, Button, Color, Column, Length, Row, Scrollable, Text,
+    TextInput,
+};
+
+use super::style;
+use super::td4_gui::{scaled, Message};
+
+#[derive(Debug, Default)]
+pub struct SourceLine {
+    pub text: String,
+    input: text_input::State,
+    delete: button::State,
+}
+
+impl SourceLine {
+    fn new(text: String) -> Self {
+        SourceLine {
+            text,
+            ..SourceLine::default()
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SourceEditor {
+    pub lines: Vec<SourceLine>,
+    pub errors: Vec<String>,
+    scroll: scrollable::State,
+    assemble: button::State,
+    load: button::State,
+}
+
+impl SourceEditor {
+    pub fn source(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.lines.iter().any(|line| line.input.is_focused())
+    }
+
+    pub fn set_source(&mut self, source: &str) {
+        self.lines = source
+            .lines()
+            .map(|line| SourceLine::new(line.to_string()))
+            .collect();
+        if self.lines.is_empty() {
+            self.lines.push(SourceLine::default());
+        }
+    }
+
+    pub fn edit(&mut self, index: usize, text: String) {
+        if let Some(line) = self.lines.get_mut(index) {
+            line.text = text;
+        }
+    }
+
+    /// Splits off a new, focused line after `index`, like pressing Enter in
+    /// a regular text editor.
+    pub fn new_line(&mut self, index: usize) {
+        if let Some(line) = self.lines.get_mut(index) {
+            line.input.unfocus();
+        }
+        let mut line = SourceLine::default();
+        line.input.focus();
+        self.lines.insert((index + 1).min(self.lines.len()), line);
+    }
+
+    pub fn delete_line(&mut self, index: usize) {
+        if index < self.lines.len() {
+            self.lines.remove(index);
+        }
+        if self.lines.is_empty() {
+            self.lines.push(SourceLine::default());
+        }
+    }
+
+    pub fn create_layout(&mut self, theme: style::Theme, scale: f32) -> Column<Message> {
+        let lines =
+            self.lines
+                .iter_mut()
+                .enumerate()
+                .fold(Column::new().spacing(2), |col, (i, line)| {
+                    col.push(
+                        Row::new()
+                            .spacing(5)
+                            .align_items(Align::Center)
+                            .push(
+                                Text::new(format!("{:2}", i + 1))
+                                    .size(14)
+                                    .width(Length::from(20)),
+                            )
+                            .push(
+                                TextInput::new(&mut line.input, "", &line.text, move |text| {
+                                    Message::SourceEdit(i, text)
+                                })
+                                .size(16)
+                                .padding(3)
+                                .on_submit(Message::SourceNewLine(i))
+                                .style(theme),
+                            )
+                            .push(
+                                Button::new(&mut line.delete, Text::new("x").size(14))
+                                    .padding(3)
+                                    .on_press(Message::SourceDeleteLine(i))
+                                    .style(theme),
+                            ),
+                    )
+                });
+
+        let controls = Row::new()
+            .spacing(5)
+            .push(
+                Button::new(&mut self.assemble, Text::new("Assemble"))
+                    .padding(5)
+                    .on_press(Message::Assemble)
+                    .style(theme),
+            )
+            .push(
+                Button::new(&mut self.load, Text::new("From ROM"))
+                    .padding(5)
+                    .on_press(Message::Disassemble)
+                    .style(theme),
+            );
+
+        let errors = self
+            .errors
+            .iter()
+            .fold(Column::new().spacing(2), |col, err| {
+                col.push(
+                    Text::new(err.as_str())
+                        .size(14)
+                        .color(Color::from_rgb(0.8, 0.0, 0.0)),
+                )
+            });
+
+        Column::new()
+            .spacing(10)
+            .width(Length::from(scaled(260.0, scale)))
+            .push(Text::new("Source"))
+            .push(
+                Scrollable::new(&mut self.scroll)
+                    .height(Length::from(scaled(420.0, scale)))
+                    .push(lines),
+            )
+            .push(controls)
+            .push(errors)
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::machine;
+use super::romfile::ROM_SIZE;
+use td4_emu::emulator::Emulator;
+
+/// Number of distinct register states: PC, A, B and output are 4 bits
+/// each, the carry flag one.
+const STATES: usize = 1 << 17;
+const UNVISITED: u32 = u32::MAX;
+
+/// PC, A, B, output and carry packed into 17 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct State(u32);
+
+impl State {
+    const RESET: State = State(0);
+
+    fn capture(cpu: &Emulator) -> State {
+        State(
+            u32::from(cpu.reg.pc & 0x0F)
+                | u32::from(cpu.reg.a & 0x0F) << 4
+                | u32::from(cpu.reg.b & 0x0F) << 8
+                | u32::from(cpu.port.output & 0x0F) << 12
+                | u32::from(cpu.reg.flag) << 16,
+        )
+    }
+
+    fn restore(self, cpu: &mut Emulator) {
+        cpu.reg.pc = (self.0 & 0x0F) as u8;
+        cpu.reg.a = (self.0 >> 4 & 0x0F) as u8;
+        cpu.reg.b = (self.0 >> 8 & 0x0F) as u8;
+        cpu.port.output = (self.0 >> 12 & 0x0F) as u8;
+        cpu.reg.flag = self.0 >> 16 & 0x01 != 0;
+    }
+
+    fn pc(self) -> u8 {
+        (self.0 & 0x0F) as u8
+    }
+
+    fn output(self) -> u8 {
+        (self.0 >> 12 & 0x0F) as u8
+    }
+}
+
+/// Executes one cycle from `state` with `input` on the input port.
+fn next(cpu: &mut Emulator, state: State, input: u8) -> State {
+    state.restore(cpu);
+    cpu.port.input = input;
+    machine::step(cpu);
+    State::capture(cpu)
+}
+
+/// A set of states the program can never leave once it gets there,
+/// whatever the input does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalLoop {
+    pub addresses: Vec<u8>,
+    /// Number of distinct register states in the loop.
+    pub states: usize,
+    /// Output values shown while in the loop.
+    pub outputs: Vec<u8>,
+}
+
+/// The deterministic run from reset with the input port held at `input`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub input: u8,
+    /// Output values in the order they appear; from `repeat` on they
+    /// repeat forever.
+    pub outputs: Vec<u8>,
+    pub repeat: usize,
+    /// Addresses executed in the repeating part.
+    pub addresses: Vec<u8>,
+}
+
+impl fmt::Display for Run {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &[u8]| {
+            values
+                .iter()
+                .map(|v| format!("{:X}", v))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        write!(f, "IN {:04b}: outputs ", self.input)?;
+        if self.repeat > 0 {
+            write!(f, "{}, then ", join(&self.outputs[..self.repeat]))?;
+        }
+        write!(
+            f,
+            "{} forever, looping at {}",
+            join(&self.outputs[self.repeat..]),
+            join(&self.addresses)
+        )
+    }
+}
+
+/// Everything the program stored in `rom` can do from reset, when the
+/// input port may change to any value on any cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub rom: [u8; ROM_SIZE],
+    /// Number of reachable states and of distinct transitions between them.
+    pub states: usize,
+    pub transitions: usize,
+    pub reachable: [bool; ROM_SIZE],
+    pub loops: Vec<TerminalLoop>,
+    /// One run per constant input value, 0 to 15.
+    pub runs: Vec<Run>,
+}
+
+impl Analysis {
+    /// Addresses no input sequence ever executes.
+    pub fn unreachable(&self) -> Vec<u8> {
+        (0..ROM_SIZE as u8)
+            .filter(|&addr| !self.reachable[addr as usize])
+            .collect()
+    }
+}
+
+fn mask_values(mask: u16) -> Vec<u8> {
+    (0..16).filter(|bit| mask & 1 << bit != 0).collect()
+}
+
+/// Builds the reachable state graph of `rom` over all input values.
+pub fn analyze(rom: &[u8; ROM_SIZE]) -> Analysis {
+    let mut cpu = Emulator::default();
+    machine::load_rom(&mut cpu, rom);
+
+    // Breadth-first search from reset; `index` maps a state to its position
+    // in `states`, whose successors are in `edges` at the same position.
+    let mut index = vec![UNVISITED; STATES];
+    let mut states = vec![State::RESET];
+    let mut edges: Vec<Vec<u32>> = Vec::new();
+    index[State::RESET.0 as usize] = 0;
+    while edges.len() < states.len() {
+        let state = states[edges.len()];
+        let mut successors = Vec::new();
+        for input in 0..16 {
+            let next = next(&mut cpu, state, input);
+            let id = match index[next.0 as usize] {
+                UNVISITED => {
+                    index[next.0 as usize] = states.len() as u32;
+                    states.push(next);
+                    states.len() as u32 - 1
+                }
+                id => id,
+            };
+            if !successors.contains(&id) {
+                successors.push(id);
+            }
+        }
+        edges.push(successors);
+    }
+
+    let mut reachable = [false; ROM_SIZE];
+    for state in states.iter() {
+        reachable[state.pc() as usize] = true;
+    }
+
+    // Terminal loops are the strongly connected components with no edge
+    // leaving them, grouped by the addresses they run through.
+    let component = components(&edges);
+    let count = component.iter().map(|&c| c as usize + 1).max().unwrap_or(0);
+    let mut terminal = vec![true; count];
+    for (id, successors) in edges.iter().enumerate() {
+        if successors
+            .iter()
+            .any(|&s| component[s as usize] != component[id])
+        {
+            terminal[component[id] as usize] = false;
+        }
+    }
+    let mut members: Vec<(u16, usize, u16)> = vec![(0, 0, 0); count];
+    for (id, state) in states.iter().enumerate() {
+        let member = &mut members[component[id] as usize];
+        member.0 |= 1 << state.pc();
+        member.1 += 1;
+        member.2 |= 1 << state.output();
+    }
+    let mut grouped: Vec<(u16, usize, u16)> = Vec::new();
+    for (c, &(addresses, size, outputs)) in members.iter().enumerate() {
+        if !terminal[c] {
+            continue;
+        }
+        match grouped.iter_mut().find(|g| g.0 == addresses) {
+            Some(group) => {
+                group.1 += size;
+                group.2 |= outputs;
+            }
+            None => grouped.push((addresses, size, outputs)),
+        }
+    }
+    grouped.sort_by_key(|g| mask_values(g.0));
+    let loops = grouped
+        .into_iter()
+        .map(|(addresses, states, outputs)| TerminalLoop {
+            addresses: mask_values(addresses),
+            states,
+            outputs: mask_values(outputs),
+        })
+        .collect();
+
+    let runs = (0..16).map(|input| run(&mut cpu, input)).collect();
+
+    Analysis {
+        rom: *rom,
+        states: states.len(),
+        transitions: edges.iter().map(Vec::len).sum(),
+        reachable,
+        loops,
+        runs,
+    }
+}
+
+/// Follows the program from reset with a constant `input` until a state
+/// repeats.
+fn run(cpu: &mut Emulator, input: u8) -> Run {
+    let mut seen = HashMap::new();
+    let mut path = Vec::new();
+    let mut state = State::RESET;
+    let start = loop {
+        if let Some(&start) = seen.get(&state) {
+            break start;
+        }
+        seen.insert(state, path.len());
+        path.push(state);
+        state = next(cpu, state, input);
+    };
+
+    let outputs = |states: &[State]| {
+        let mut outputs = states.iter().map(|s| s.output()).collect::<Vec<_>>();
+        outputs.dedup();
+        outputs
+    };
+    let mut prefix = outputs(&path[..start]);
+    let mut cycle = outputs(&path[start..]);
+    if cycle.len() > 1 && cycle.first() == cycle.last() {
+        cycle.pop();
+    }
+    if prefix.last() == cycle.first() {
+        prefix.pop();
+    }
+    let addresses = path[start..]
+        .iter()
+        .fold(0u16, |mask, s| mask | 1 << s.pc());
+
+    Run {
+        input,
+        repeat: prefix.len(),
+        outputs: prefix.into_iter().chain(cycle).collect(),
+        addresses: mask_values(addresses),
+    }
+}
+
+/// Strongly connected component of every node, by Tarjan's algorithm
+/// without recursion since the graph can have over a hundred thousand
+/// nodes.
+fn components(edges: &[Vec<u32>]) -> Vec<u32> {
+    let n = edges.len();
+    let mut order = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut component = vec![UNVISITED; n];
+    let mut stack = Vec::new();
+    let mut on_stack = vec![false; n];
+    let mut next_order = 0;
+    let mut next_component = 0;
+
+    for root in 0..n {
+        if order[root] != UNVISITED {
+            continue;
+        }
+        // Each frame is a node and the position of the next edge to follow.
+        let mut frames = vec![(root, 0)];
+        order[root] = next_order;
+        low[root] = next_order;
+        next_order += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&mut (node, ref mut edge)) = frames.last_mut() {
+            if let Some(&succ) = edges[node].get(*edge) {
+                *edge += 1;
+                let succ = succ as usize;
+                if order[succ] == UNVISITED {
+                    order[succ] = next_order;
+                    low[succ] = next_order;
+                    next_order += 1;
+                    stack.push(succ);
+                    on_stack[succ] = true;
+                    frames.push((succ, 0));
+                } else if on_stack[succ] {
+                    low[node] = low[node].min(order[succ]);
+                }
+                continue;
+            }
+
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == order[node] {
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component[member] = next_component;
+                    if member == node {
+                        break;
+                    }
+                }
+                next_component += 1;
+            }
+        }
+    }
+    component
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm;
+
+    fn analyze_source(source: &str) -> Analysis {
+        analyze(&asm::assemble(source).unwrap())
+    }
+
+    #[test]
+    fn straight_line_program_wraps_around() {
+        let analysis = analyze_source("OUT 0001\nOUT 0010");
+        assert_eq!(analysis.states, 17);
+        assert_eq!(analysis.transitions, 17);
+        assert!(analysis.unreachable().is_empty());
+        assert_eq!(
+            analysis.loops,
+            vec![TerminalLoop {
+                addresses: (0..16).collect(),
+                states: 16,
+                outputs: vec![1, 2],
+            }]
+        );
+        for run in analysis.runs.iter() {
+            assert_eq!(run.outputs, vec![0, 1, 2]);
+            assert_eq!(run.repeat, 1);
+        }
+    }
+
+    #[test]
+    fn self_loop_is_terminal() {
+        let analysis = analyze_source("OUT 0101\nJMP 1");
+        assert_eq!(analysis.states, 2);
+        assert_eq!(analysis.unreachable(), (2..16).collect::<Vec<_>>());
+        assert_eq!(
+            analysis.loops,
+            vec![TerminalLoop {
+                addresses: vec![1],
+                states: 1,
+                outputs: vec![5],
+            }]
+        );
+        assert_eq!(
+            analysis.runs[7],
+            Run {
+                input: 7,
+                outputs: vec![0, 5],
+                repeat: 1,
+                addresses: vec![1],
+            }
+        );
+        assert_eq!(
+            analysis.runs[7].to_string(),
+            "IN 0111: outputs 0, then 5 forever, looping at 1"
+        );
+    }
+
+    #[test]
+    fn code_skipped_by_jumps_is_unreachable() {
+        // Nothing sets carry, so the JNC always jumps over address 2.
+        let analysis = analyze_source("IN A\nJNC 3\nOUT 1111\nOUT 0011\nJMP 4");
+        assert_eq!(analysis.states, 1 + 16 * 3);
+        assert_eq!(
+            analysis.unreachable(),
+            vec![2, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+        );
+        assert_eq!(
+            analysis.loops,
+            vec![TerminalLoop {
+                addresses: vec![4],
+                states: 16,
+                outputs: vec![3],
+            }]
+        );
+        assert!(analysis.runs.iter().all(|run| run.outputs == vec![0, 3]));
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::trace::Sample;
+
+/// What to do when the program is found to be halted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HaltAction {
+    #[default]
+    Stop,
+    Warn,
+    Ignore,
+}
+
+impl HaltAction {
+    pub const ALL: [HaltAction; 3] = [HaltAction::Stop, HaltAction::Warn, HaltAction::Ignore];
+}
+
+impl fmt::Display for HaltAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HaltAction::Stop => "Stop",
+            HaltAction::Warn => "Warn",
+            HaltAction::Ignore => "Ignore",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Notices when the machine keeps cycling through states it has already
+/// been in without changing its output. The machine is deterministic, so
+/// unless the input changes it will loop like that forever, e.g. at the
+/// usual `JMP` to the same address that ends a program.
+#[derive(Debug, Default)]
+pub struct LoopDetector {
+    /// Cycle each state was last seen at since the last [`LoopDetector::clear`].
+    seen: HashMap<u32, u64>,
+    output: Option<u8>,
+    output_changed: u64,
+    /// Cycle of the latest `IN` reading input nobody scripted.
+    input_read: Option<u64>,
+    reported: bool,
+}
+
+/// Packs the registers and the output port; the input port is left out as
+/// the detector is cleared whenever it changes.
+fn key(sample: &Sample) -> u32 {
+    u32::from(sample.pc & 0x0F)
+        | u32::from(sample.a & 0x0F) << 4
+        | u32::from(sample.b & 0x0F) << 8
+        | u32::from(sample.output & 0x0F) << 12
+        | u32::from(sample.carry) << 16
+}
+
+impl LoopDetector {
+    /// Forgets every state, e.g. after the input or the program changed.
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.output = None;
+        self.input_read = None;
+        self.reported = false;
+    }
+
+    /// Notes that the instruction executed at `cycle` reads the input port
+    /// while it may be changed by hand. A loop running through it may just
+    /// be waiting for a button and is not reported.
+    pub fn read_input(&mut self, cycle: u64) {
+        self.input_read = Some(cycle);
+    }
+
+    /// Records the state in `sample`. Returns `true` the first time a state
+    /// comes back with the output unchanged and no input read since it was
+    /// last seen.
+    pub fn observe(&mut self, sample: &Sample) -> bool {
+        if self.output != Some(sample.output) {
+            self.output = Some(sample.output);
+            self.output_changed = sample.cycle;
+        }
+        match self.seen.insert(key(sample), sample.cycle) {
+            Some(cycle)
+                if cycle < sample.cycle
+                    && cycle >= self.output_changed
+                    && self.input_read.is_none_or(|read| read < cycle) =>
+            {
+                let first = !self.reported;
+                self.reported = true;
+                first
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+
+use td4_emu::emulator::Emulator;
+
+pub const HISTORY_DEPTH: usize = 1024;
+
+/// Everything needed to put the emulator back where it was. The ROM is
+/// left out: programs cannot change it, and edits to it are undone through
+/// the undo stack instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    pub pc: u8,
+    pub a: u8,
+    pub b: u8,
+    pub flag: bool,
+    pub input: u8,
+    pub output: u8,
+    /// Cycle count and simulated time in milliseconds at the snapshot.
+    pub cycles: u64,
+    pub elapsed: u64,
+}
+
+impl Snapshot {
+    pub fn capture(cpu: &Emulator, cycles: u64, elapsed: u64) -> Self {
+        Snapshot {
+            pc: cpu.reg.pc,
+            a: cpu.reg.a,
+            b: cpu.reg.b,
+            flag: cpu.reg.flag,
+            input: cpu.port.input,
+            output: cpu.port.output,
+            cycles,
+            elapsed,
+        }
+    }
+
+    pub fn restore(&self, cpu: &mut Emulator) {
+        cpu.reg.pc = self.pc;
+        cpu.reg.a = self.a;
+        cpu.reg.b = self.b;
+        cpu.reg.flag = self.flag;
+        cpu.port.input = self.input;
+        cpu.port.output = self.output;
+    }
+}
+
+/// Ring buffer of prior machine states; the oldest entry is dropped once
+/// `capacity` is reached.
+#[derive(Debug)]
+pub struct History {
+    entries: VecDeque<Snapshot>,
+    capacity: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(HISTORY_DEPTH)
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(snapshot);
+    }
+
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        self.entries.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
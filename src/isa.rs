@@ -0,0 +1,84 @@
+use std::fmt;
+
+/// One TD4 instruction. The upper nibble of a ROM byte selects the
+/// operation and the lower nibble is the immediate operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    AddA(u8),
+    MovAB,
+    InA,
+    MovA(u8),
+    MovBA,
+    AddB(u8),
+    InB,
+    MovB(u8),
+    OutB,
+    Out(u8),
+    Jnc(u8),
+    Jmp(u8),
+}
+
+impl Instruction {
+    /// Returns `None` for the opcodes the TD4 leaves undefined
+    /// (`1000`, `1010`, `1100` and `1101`).
+    pub fn decode(byte: u8) -> Option<Instruction> {
+        let im = byte & 0x0F;
+        let inst = match byte >> 4 {
+            0b0000 => Instruction::AddA(im),
+            0b0001 => Instruction::MovAB,
+            0b0010 => Instruction::InA,
+            0b0011 => Instruction::MovA(im),
+            0b0100 => Instruction::MovBA,
+            0b0101 => Instruction::AddB(im),
+            0b0110 => Instruction::InB,
+            0b0111 => Instruction::MovB(im),
+            0b1001 => Instruction::OutB,
+            0b1011 => Instruction::Out(im),
+            0b1110 => Instruction::Jnc(im),
+            0b1111 => Instruction::Jmp(im),
+            _ => return None,
+        };
+        Some(inst)
+    }
+
+    pub fn encode(&self) -> u8 {
+        match *self {
+            Instruction::AddA(im) => im & 0x0F,
+            Instruction::MovAB => 0b0001_0000,
+            Instruction::InA => 0b0010_0000,
+            Instruction::MovA(im) => 0b0011_0000 | (im & 0x0F),
+            Instruction::MovBA => 0b0100_0000,
+            Instruction::AddB(im) => 0b0101_0000 | (im & 0x0F),
+            Instruction::InB => 0b0110_0000,
+            Instruction::MovB(im) => 0b0111_0000 | (im & 0x0F),
+            Instruction::OutB => 0b1001_0000,
+            Instruction::Out(im) => 0b1011_0000 | (im & 0x0F),
+            Instruction::Jnc(im) => 0b1110_0000 | (im & 0x0F),
+            Instruction::Jmp(im) => 0b1111_0000 | (im & 0x0F),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::AddA(im) => write!(f, "ADD A,{}", im),
+            Instruction::MovAB => write!(f, "MOV A,B"),
+            Instruction::InA => write!(f, "IN A"),
+            Instruction::MovA(im) => write!(f, "MOV A,{}", im),
+            Instruction::MovBA => write!(f, "MOV B,A"),
+            Instruction::AddB(im) => write!(f, "ADD B,{}", im),
+            Instruction::InB => write!(f, "IN B"),
+            Instruction::MovB(im) => write!(f, "MOV B,{}", im),
+            Instruction::OutB => write!(f, "OUT B"),
+            Instruction::Out(im) => write!(f, "OUT {:04b}", im),
+            Instruction::Jnc(im) => write!(f, "JNC {}", im),
+            Instruction::Jmp(im) => write!(f, "JMP {}", im),
+        }
+    }
+}
+
+/// Mnemonic text for a ROM byte, or `None` if the opcode is undefined.
+pub fn disassemble(byte: u8) -> Option<String> {
+    Instruction::decode(byte).map(|inst| inst.to_string())
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+
+use iced_native::keyboard::KeyCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    ToggleInput(u8),
+    Step,
+    RunStop,
+    Reset,
+    HoldMode,
+    Help,
+}
+
+impl Action {
+    const ALL: [Action; 9] = [
+        Action::ToggleInput(3),
+        Action::ToggleInput(2),
+        Action::ToggleInput(1),
+        Action::ToggleInput(0),
+        Action::Step,
+        Action::RunStop,
+        Action::Reset,
+        Action::HoldMode,
+        Action::Help,
+    ];
+
+    /// Name used in key binding files.
+    fn name(self) -> &'static str {
+        match self {
+            Action::ToggleInput(3) => "input3",
+            Action::ToggleInput(2) => "input2",
+            Action::ToggleInput(1) => "input1",
+            Action::ToggleInput(_) => "input0",
+            Action::Step => "step",
+            Action::RunStop => "run-stop",
+            Action::Reset => "reset",
+            Action::HoldMode => "hold-mode",
+            Action::Help => "help",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == text)
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::ToggleInput(bit) => write!(f, "Toggle input bit {}", bit),
+            Action::Step => write!(f, "Step"),
+            Action::RunStop => write!(f, "Run / Stop"),
+            Action::Reset => write!(f, "Reset"),
+            Action::HoldMode => write!(f, "Switch all input bits latching / momentary"),
+            Action::Help => write!(f, "Show / hide this help"),
+        }
+    }
+}
+
+const KEY_NAMES: [(&str, KeyCode); 52] = [
+    ("0", KeyCode::Key0),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("Space", KeyCode::Space),
+    ("Enter", KeyCode::Enter),
+    ("Escape", KeyCode::Escape),
+    ("Backspace", KeyCode::Backspace),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+];
+
+pub fn key_name(key: KeyCode) -> String {
+    KEY_NAMES
+        .iter()
+        .find(|(_, code)| *code == key)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| format!("{:?}", key))
+}
+
+fn parse_key(text: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(text))
+        .map(|(_, code)| *code)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    bindings: Vec<(KeyCode, Action)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap {
+            bindings: vec![
+                (KeyCode::Key1, Action::ToggleInput(3)),
+                (KeyCode::Key2, Action::ToggleInput(2)),
+                (KeyCode::Key3, Action::ToggleInput(1)),
+                (KeyCode::Key4, Action::ToggleInput(0)),
+                (KeyCode::Space, Action::Step),
+                (KeyCode::Enter, Action::RunStop),
+                (KeyCode::R, Action::Reset),
+                (KeyCode::M, Action::HoldMode),
+                (KeyCode::F1, Action::Help),
+            ],
+        }
+    }
+}
+
+impl KeyMap {
+    /// Parses `key = action` lines, e.g. `Space = step`. Bindings not
+    /// mentioned keep their default keys.
+    pub fn parse(text: &str) -> Result<KeyMap, String> {
+        let mut keymap = KeyMap::default();
+        for (i, line) in text.lines().enumerate() {
+            keymap.parse_line(i + 1, line)?;
+        }
+        Ok(keymap)
+    }
+
+    /// Applies one `key = action` line; blank lines and `#` comments are
+    /// skipped.
+    pub fn parse_line(&mut self, line_no: usize, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let mut parts = line.splitn(2, '=').map(str::trim);
+        let key = parts.next().and_then(parse_key);
+        let action = parts.next().and_then(Action::parse);
+        match (key, action) {
+            (Some(key), Some(action)) => {
+                self.bind(key, action);
+                Ok(())
+            }
+            _ => Err(format!("line {}: invalid binding \"{}\"", line_no, line)),
+        }
+    }
+
+    /// Formats the bindings as `key = action` lines that [`KeyMap::parse`]
+    /// reads back.
+    pub fn format(&self) -> String {
+        self.bindings
+            .iter()
+            .map(|(key, action)| format!("{} = {}\n", key_name(*key), action.name()))
+            .collect()
+    }
+
+    /// Binds `key` to `action`, replacing the key previously used for it.
+    pub fn bind(&mut self, key: KeyCode, action: Action) {
+        self.bindings.retain(|&(k, a)| k != key && a != action);
+        self.bindings.push((key, action));
+    }
+
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, action)| *action)
+    }
+
+    pub fn bindings(&self) -> &[(KeyCode, Action)] {
+        &self.bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_parses_back() {
+        let mut keymap = KeyMap::default();
+        keymap.bind(KeyCode::Left, Action::ToggleInput(3));
+        keymap.bind(KeyCode::F5, Action::RunStop);
+        assert_eq!(KeyMap::parse(&keymap.format()), Ok(keymap));
+    }
+
+    #[test]
+    fn parse_rebinds_only_listed_actions() {
+        let keymap = KeyMap::parse("# arrows\n\nleft = input3\n  S = step  \n").unwrap();
+        assert_eq!(keymap.action(KeyCode::Left), Some(Action::ToggleInput(3)));
+        assert_eq!(keymap.action(KeyCode::Key1), None);
+        assert_eq!(keymap.action(KeyCode::S), Some(Action::Step));
+        assert_eq!(keymap.action(KeyCode::Space), None);
+        assert_eq!(keymap.action(KeyCode::R), Some(Action::Reset));
+    }
+
+    #[test]
+    fn parse_rejects_bad_lines() {
+        assert_eq!(
+            KeyMap::parse("Space = step\nHyper = step"),
+            Err(String::from("line 2: invalid binding \"Hyper = step\""))
+        );
+        assert!(KeyMap::parse("Space = jump").is_err());
+        assert!(KeyMap::parse("Space").is_err());
+    }
+}
@@ -0,0 +1,14 @@
+pub mod asm;
+pub mod clip;
+pub mod clock;
+pub mod explore;
+pub mod halt;
+pub mod history;
+pub mod isa;
+pub mod lint;
+pub mod machine;
+pub mod romfile;
+pub mod stimulus;
+pub mod trace;
+pub mod undo;
+pub mod vcd;
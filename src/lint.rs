@@ -0,0 +1,345 @@
+use std::fmt;
+
+use super::isa::Instruction;
+use super::romfile::ROM_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    A,
+    B,
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::A => write!(f, "A"),
+            Register::B => write!(f, "B"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    UndefinedOpcode,
+    /// No instruction that can run just before this `JNC` sets carry, so
+    /// it always jumps.
+    JncWithoutCarry,
+    UnreadWrite(Register),
+    /// Code after the `JMP` to itself at this address that nothing jumps to.
+    AfterSelfJump(u8),
+    /// `ADD` whose register is known to hold `value` here, so the sum
+    /// wraps to `result`.
+    Overflow {
+        register: Register,
+        value: u8,
+        result: u8,
+    },
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::UndefinedOpcode => write!(f, "undefined opcode"),
+            LintKind::JncWithoutCarry => {
+                write!(
+                    f,
+                    "JNC always jumps: no instruction before it can set carry"
+                )
+            }
+            LintKind::UnreadWrite(register) => {
+                write!(f, "the value written to {} is never read", register)
+            }
+            LintKind::AfterSelfJump(addr) => {
+                write!(f, "unreachable after the self-jump at {}", addr)
+            }
+            LintKind::Overflow {
+                register,
+                value,
+                result,
+            } => write!(
+                f,
+                "overflows: {} is {} here and becomes {} with carry set",
+                register, value, result
+            ),
+        }
+    }
+}
+
+/// A warning about the instruction at `addr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lint {
+    pub addr: u8,
+    pub kind: LintKind,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.addr, self.kind)
+    }
+}
+
+/// What is known about a register's value at an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    /// No path reaches the address.
+    Unset,
+    Known(u8),
+    Varying,
+}
+
+impl Value {
+    fn join(self, other: Value) -> Value {
+        match (self, other) {
+            (Value::Unset, v) | (v, Value::Unset) => v,
+            (Value::Known(a), Value::Known(b)) if a == b => Value::Known(a),
+            _ => Value::Varying,
+        }
+    }
+}
+
+fn next(addr: usize) -> usize {
+    (addr + 1) % ROM_SIZE
+}
+
+/// Addresses that may run after `addr`. Undefined opcodes fall through.
+fn successors(inst: Option<Instruction>, addr: usize) -> Vec<usize> {
+    match inst {
+        Some(Instruction::Jmp(im)) => vec![im as usize],
+        Some(Instruction::Jnc(im)) => vec![im as usize, next(addr)],
+        _ => vec![next(addr)],
+    }
+}
+
+/// Registers an instruction reads and writes, as `(reads, writes)` masks
+/// with bit 0 for A and bit 1 for B.
+fn accesses(inst: Option<Instruction>) -> (u8, u8) {
+    const A: u8 = 0b01;
+    const B: u8 = 0b10;
+    match inst {
+        Some(Instruction::AddA(_)) => (A, A),
+        Some(Instruction::MovAB) => (B, A),
+        Some(Instruction::InA) | Some(Instruction::MovA(_)) => (0, A),
+        Some(Instruction::MovBA) => (A, B),
+        Some(Instruction::AddB(_)) => (B, B),
+        Some(Instruction::InB) | Some(Instruction::MovB(_)) => (0, B),
+        Some(Instruction::OutB) => (B, 0),
+        _ => (0, 0),
+    }
+}
+
+/// Register values after executing `inst` with `values` before it.
+fn transfer(inst: Option<Instruction>, [a, b]: [Value; 2]) -> [Value; 2] {
+    let add = |value, im: u8| match value {
+        Value::Known(v) => Value::Known((v + im) & 0x0F),
+        other => other,
+    };
+    match inst {
+        Some(Instruction::AddA(im)) => [add(a, im), b],
+        Some(Instruction::MovAB) => [b, b],
+        Some(Instruction::InA) => [Value::Varying, b],
+        Some(Instruction::MovA(im)) => [Value::Known(im), b],
+        Some(Instruction::MovBA) => [a, a],
+        Some(Instruction::AddB(im)) => [a, add(b, im)],
+        Some(Instruction::InB) => [a, Value::Varying],
+        Some(Instruction::MovB(im)) => [a, Value::Known(im)],
+        _ => [a, b],
+    }
+}
+
+/// Checks the program in `rom` for common mistakes, in address order.
+pub fn lint(rom: &[u8; ROM_SIZE]) -> Vec<Lint> {
+    let insts = rom
+        .iter()
+        .map(|&byte| Instruction::decode(byte))
+        .collect::<Vec<_>>();
+    let succs = (0..ROM_SIZE)
+        .map(|addr| successors(insts[addr], addr))
+        .collect::<Vec<_>>();
+
+    // Register values on entry to each address, starting from reset. This
+    // also tells which addresses are reachable at all.
+    let mut entry = [[Value::Unset; 2]; ROM_SIZE];
+    entry[0] = [Value::Known(0); 2];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for addr in 0..ROM_SIZE {
+            if entry[addr][0] == Value::Unset {
+                continue;
+            }
+            let out = transfer(insts[addr], entry[addr]);
+            for &succ in succs[addr].iter() {
+                let joined = [entry[succ][0].join(out[0]), entry[succ][1].join(out[1])];
+                if joined != entry[succ] {
+                    entry[succ] = joined;
+                    changed = true;
+                }
+            }
+        }
+    }
+    let reachable = |addr: usize| entry[addr][0] != Value::Unset;
+
+    // Registers whose value may still be read after each address.
+    let mut live_out = [0u8; ROM_SIZE];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for addr in (0..ROM_SIZE).rev() {
+            let live = succs[addr].iter().fold(0, |live, &succ| {
+                let (reads, writes) = accesses(insts[succ]);
+                live | reads | (live_out[succ] & !writes)
+            });
+            if live != live_out[addr] {
+                live_out[addr] = live;
+                changed = true;
+            }
+        }
+    }
+
+    // Whether carry may be set after each address.
+    let sets_carry = |addr: usize| {
+        let [a, b] = entry[addr];
+        let overflows = |value, im: u8| match value {
+            Value::Known(v) => v + im > 0x0F,
+            _ => im > 0,
+        };
+        match insts[addr] {
+            Some(Instruction::AddA(im)) => overflows(a, im),
+            Some(Instruction::AddB(im)) => overflows(b, im),
+            _ => false,
+        }
+    };
+    let followed_by_jnc = |addr: usize| matches!(insts[next(addr)], Some(Instruction::Jnc(_)));
+
+    let mut lints = Vec::new();
+    let mut push = |addr: usize, kind| {
+        lints.push(Lint {
+            addr: addr as u8,
+            kind,
+        })
+    };
+    let mut self_jump = None;
+    for addr in 0..ROM_SIZE {
+        let inst = insts[addr];
+        if inst.is_none() {
+            push(addr, LintKind::UndefinedOpcode);
+        }
+        if !reachable(addr) {
+            // Zero bytes are the usual padding and not worth a warning.
+            if let (Some(jump), true) = (self_jump, rom[addr] != 0) {
+                push(addr, LintKind::AfterSelfJump(jump));
+            }
+            continue;
+        }
+        if inst == Some(Instruction::Jmp(addr as u8)) {
+            self_jump = Some(addr as u8);
+        }
+
+        if let Some(Instruction::Jnc(_)) = inst {
+            let carry = (0..ROM_SIZE)
+                .filter(|&pred| reachable(pred) && succs[pred].contains(&addr))
+                .any(sets_carry);
+            if !carry {
+                push(addr, LintKind::JncWithoutCarry);
+            }
+        }
+
+        let (_, writes) = accesses(inst);
+        // An `ADD` right before a `JNC` may be there only for its carry.
+        let carry_only = matches!(
+            inst,
+            Some(Instruction::AddA(_)) | Some(Instruction::AddB(_))
+        ) && followed_by_jnc(addr);
+        for (bit, register) in [(0b01, Register::A), (0b10, Register::B)].iter() {
+            if writes & bit != 0 && live_out[addr] & bit == 0 && !carry_only {
+                push(addr, LintKind::UnreadWrite(*register));
+            }
+        }
+
+        let (register, value, im) = match (inst, entry[addr]) {
+            (Some(Instruction::AddA(im)), [Value::Known(v), _]) => (Register::A, v, im),
+            (Some(Instruction::AddB(im)), [_, Value::Known(v)]) => (Register::B, v, im),
+            _ => continue,
+        };
+        if value + im > 0x0F && !followed_by_jnc(addr) {
+            push(
+                addr,
+                LintKind::Overflow {
+                    register,
+                    value,
+                    result: (value + im) & 0x0F,
+                },
+            );
+        }
+    }
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm;
+
+    fn lint_source(source: &str) -> Vec<(u8, LintKind)> {
+        lint(&asm::assemble(source).unwrap())
+            .into_iter()
+            .map(|lint| (lint.addr, lint.kind))
+            .collect()
+    }
+
+    #[test]
+    fn clean_programs_report_nothing() {
+        assert_eq!(lint_source("IN A\nMOV B,A\nOUT B\nJMP 0"), vec![]);
+        assert_eq!(
+            lint_source("MOV B,1\nADD B,15\nJNC 0\nOUT 1111\nJMP 4"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn undefined_opcode() {
+        assert_eq!(
+            lint_source("IN B\nOUT B\nDB 0x80\nJMP 0"),
+            vec![(2, LintKind::UndefinedOpcode)]
+        );
+    }
+
+    #[test]
+    fn jnc_without_carry() {
+        assert_eq!(
+            lint_source("loop: IN B\nOUT B\nJNC loop\nend: JMP end"),
+            vec![(2, LintKind::JncWithoutCarry)]
+        );
+    }
+
+    #[test]
+    fn unread_write() {
+        assert_eq!(
+            lint_source("MOV A,3\nADD A,1\nOUT 0101\nJMP 3"),
+            vec![(1, LintKind::UnreadWrite(Register::A))]
+        );
+    }
+
+    #[test]
+    fn code_after_self_jump() {
+        assert_eq!(
+            lint_source("OUT 0001\nJMP 1\nOUT 0010"),
+            vec![(2, LintKind::AfterSelfJump(1))]
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(
+            lint_source("IN B\nMOV A,12\nADD A,5\nOUT B\nMOV B,A\nOUT B\nJMP 0"),
+            vec![(
+                2,
+                LintKind::Overflow {
+                    register: Register::A,
+                    value: 12,
+                    result: 1,
+                }
+            )]
+        );
+    }
+}
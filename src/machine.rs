@@ -0,0 +1,698 @@
+use super::clock::ClockSource;
+use super::halt::{HaltAction, LoopDetector};
+use super::history::{History, Snapshot};
+use super::isa::Instruction;
+use super::romfile::ROM_SIZE;
+use super::stimulus::Stimulus;
+use super::trace::{Sample, Trace};
+use td4_emu::emulator::Emulator;
+
+pub const DEFAULT_PERIOD: u64 = 300;
+pub const MIN_PERIOD: u64 = 100;
+pub const MAX_PERIOD: u64 = 1000;
+
+/// Executes one instruction. Both the GUI and `td4-run` go through here so
+/// their traces are identical.
+pub fn step(cpu: &mut Emulator) {
+    let (opecode, operand) = cpu.fetch_decode();
+    let next_pc = cpu.exec_mut(&opecode, operand);
+    cpu.reg.pc = next_pc;
+}
+
+/// Copies a ROM image into program memory.
+pub fn load_rom(cpu: &mut Emulator, rom: &[u8]) {
+    for (dst, src) in cpu.prg.mem.iter_mut().zip(rom.iter()) {
+        *dst = *src;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunState {
+    #[default]
+    Idle,
+    Active,
+    Reverse,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    RunBack,
+    Stop,
+    Step,
+    StepBack,
+    Tick,
+    Reset,
+    LoadRom([u8; ROM_SIZE]),
+    SetRomBit(usize, u8, bool),
+    SetRomByte(usize, u8),
+    SetInput(u8),
+    SetInputBit(u8, bool),
+    SetPeriod(u64),
+    SetClock(ClockSource),
+    ToggleBreakpoint(usize),
+    LoadStimulus(Stimulus),
+    SetHaltAction(HaltAction),
+}
+
+/// Noteworthy outcomes of a [`Command`] the front end may want to report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Breakpoint(u8),
+    HistoryExhausted,
+    /// The program loops at this address without changing its output and
+    /// no input change is pending.
+    Halted(u8),
+}
+
+/// GUI-independent controller owning the emulator and everything needed to
+/// drive it: run state, clock period, breakpoints, input stimulus,
+/// execution history and the trace of every cycle.
+#[derive(Debug)]
+pub struct Machine {
+    cpu: Emulator,
+    state: RunState,
+    period: u64,
+    clock: ClockSource,
+    breakpoints: [bool; ROM_SIZE],
+    history: History,
+    stimulus: Stimulus,
+    trace: Trace,
+    halt: HaltAction,
+    loops: LoopDetector,
+    cycles: u64,
+    elapsed: u64,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        let mut machine = Machine {
+            cpu: Emulator::default(),
+            state: RunState::Idle,
+            period: DEFAULT_PERIOD,
+            clock: ClockSource::default(),
+            breakpoints: [false; ROM_SIZE],
+            history: History::default(),
+            stimulus: Stimulus::default(),
+            trace: Trace::default(),
+            halt: HaltAction::default(),
+            loops: LoopDetector::default(),
+            cycles: 0,
+            elapsed: 0,
+        };
+        machine.record();
+        machine
+    }
+}
+
+impl Machine {
+    pub fn cpu(&self) -> &Emulator {
+        &self.cpu
+    }
+
+    pub fn state(&self) -> RunState {
+        self.state
+    }
+
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    pub fn clock(&self) -> ClockSource {
+        self.clock
+    }
+
+    /// Milliseconds between ticks for the current clock source, or `None`
+    /// if the machine must not be ticked automatically.
+    pub fn interval(&self) -> Option<u64> {
+        self.clock.interval(self.period)
+    }
+
+    pub fn breakpoints(&self) -> &[bool; ROM_SIZE] {
+        &self.breakpoints
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn stimulus(&self) -> &Stimulus {
+        &self.stimulus
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    pub fn halt_action(&self) -> HaltAction {
+        self.halt
+    }
+
+    /// Instructions executed since the last reset.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Simulated time in milliseconds, summing the clock period of every
+    /// executed cycle.
+    pub fn elapsed(&self) -> u64 {
+        self.elapsed
+    }
+
+    pub fn rom(&self) -> [u8; ROM_SIZE] {
+        let mut rom = [0; ROM_SIZE];
+        for (dst, src) in rom.iter_mut().zip(self.cpu.prg.mem.iter()) {
+            *dst = *src;
+        }
+        rom
+    }
+
+    pub fn apply(&mut self, command: Command) -> Option<Event> {
+        match command {
+            Command::Run => {
+                if self.clock != ClockSource::Manual {
+                    self.state = RunState::Active;
+                }
+            }
+            Command::RunBack => {
+                if self.clock != ClockSource::Manual {
+                    self.state = RunState::Reverse;
+                }
+            }
+            Command::Stop => self.state = RunState::Idle,
+            Command::Step => {
+                if self.state == RunState::Idle {
+                    return self.step();
+                }
+            }
+            Command::StepBack => {
+                if self.state == RunState::Idle && !self.step_back() {
+                    return Some(Event::HistoryExhausted);
+                }
+            }
+            Command::Tick => return self.tick(),
+            Command::Reset => {
+                self.push_history();
+                self.reset_registers();
+            }
+            Command::LoadRom(rom) => {
+                load_rom(&mut self.cpu, &rom);
+                self.reset_registers();
+                self.state = RunState::Idle;
+                self.history.clear();
+            }
+            Command::SetRomBit(addr, bit, value) => {
+                let byte = self.cpu.prg.mem[addr];
+                self.cpu.prg.mem[addr] = (byte & !(0x01 << bit)) | ((value as u8) << bit);
+                self.loops.clear();
+            }
+            Command::SetRomByte(addr, value) => {
+                self.cpu.prg.mem[addr] = value;
+                self.loops.clear();
+            }
+            Command::SetInput(value) => {
+                self.set_input(value & 0x0F);
+            }
+            Command::SetInputBit(bit, value) => {
+                let input = self.cpu.port.input;
+                self.set_input((input & !(0x01 << bit)) | ((value as u8) << bit));
+            }
+            Command::SetPeriod(period) => self.period = period.clamp(MIN_PERIOD, MAX_PERIOD),
+            Command::SetClock(clock) => {
+                self.clock = clock;
+                if clock == ClockSource::Manual {
+                    self.state = RunState::Idle;
+                }
+            }
+            Command::ToggleBreakpoint(addr) => {
+                self.breakpoints[addr] = !self.breakpoints[addr];
+            }
+            Command::LoadStimulus(stimulus) => {
+                self.stimulus = stimulus;
+                self.loops.clear();
+            }
+            Command::SetHaltAction(halt) => {
+                self.halt = halt;
+                self.loops.clear();
+            }
+        }
+        None
+    }
+
+    fn tick(&mut self) -> Option<Event> {
+        let mut event = None;
+        for _ in 0..self.clock.batch() {
+            match self.state {
+                RunState::Idle => return event,
+                RunState::Active => {
+                    if let Some(halted) = self.step() {
+                        event = Some(halted);
+                    }
+                }
+                RunState::Reverse => {
+                    if !self.step_back() {
+                        self.state = RunState::Idle;
+                        return Some(Event::HistoryExhausted);
+                    }
+                }
+            }
+
+            let pc = self.cpu.reg.pc;
+            if self.breakpoints[pc as usize] {
+                self.state = RunState::Idle;
+                return Some(Event::Breakpoint(pc));
+            }
+        }
+        event
+    }
+
+    /// Executes one cycle and reports a halt as configured by the
+    /// [`HaltAction`], stopping a run if asked to.
+    fn step(&mut self) -> Option<Event> {
+        self.push_history();
+        let period = self.clock.period(self.period).unwrap_or(0);
+        if let Some(input) = self.stimulus.due(self.cycles, self.elapsed, period) {
+            self.set_input(input);
+        }
+        // Seeds the detector with the state the program starts from.
+        self.observe();
+        let inst = Instruction::decode(self.cpu.prg.mem[self.cpu.reg.pc as usize]);
+        if matches!(inst, Some(Instruction::InA) | Some(Instruction::InB))
+            && self.stimulus.is_empty()
+        {
+            self.loops.read_input(self.cycles);
+        }
+        step(&mut self.cpu);
+        self.cycles += 1;
+        self.elapsed += period;
+        self.record();
+        if !self.observe() {
+            return None;
+        }
+
+        match self.halt {
+            HaltAction::Stop => self.state = RunState::Idle,
+            HaltAction::Warn => {}
+            HaltAction::Ignore => return None,
+        }
+        Some(Event::Halted(self.cpu.reg.pc))
+    }
+
+    /// Feeds the current state to the loop detector, unless halts are
+    /// ignored or the stimulus will still change the input.
+    fn observe(&mut self) -> bool {
+        if self.halt == HaltAction::Ignore {
+            return false;
+        }
+        let period = self.clock.period(self.period).unwrap_or(0);
+        if !self
+            .stimulus
+            .upcoming(self.cycles, self.elapsed, period)
+            .is_empty()
+        {
+            return false;
+        }
+        self.loops.observe(&Sample::capture(self.cycles, &self.cpu))
+    }
+
+    fn set_input(&mut self, value: u8) {
+        if self.cpu.port.input != value {
+            self.loops.clear();
+        }
+        self.cpu.port.input = value;
+        self.record();
+    }
+
+    fn push_history(&mut self) {
+        self.history
+            .push(Snapshot::capture(&self.cpu, self.cycles, self.elapsed));
+    }
+
+    fn step_back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(snapshot) => {
+                snapshot.restore(&mut self.cpu);
+                self.cycles = snapshot.cycles;
+                self.elapsed = snapshot.elapsed;
+                self.trace.truncate(self.cycles);
+                self.loops.clear();
+                self.record();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn reset_registers(&mut self) {
+        self.cpu.reg = td4_emu::reg::Reg::default();
+        self.cpu.port = td4_emu::port::Port::default();
+        self.cycles = 0;
+        self.elapsed = 0;
+        self.trace.clear();
+        self.loops.clear();
+        self.record();
+    }
+
+    fn record(&mut self) {
+        self.trace.record(Sample::capture(self.cycles, &self.cpu));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine() -> Machine {
+        // MOV A,3 / ADD A,1 / OUT 0101 / JMP 3
+        let mut rom = [0; ROM_SIZE];
+        rom[..4].copy_from_slice(&[0b0011_0011, 0b0000_0001, 0b1011_0101, 0b1111_0011]);
+
+        let mut machine = Machine::default();
+        machine.apply(Command::LoadRom(rom));
+        machine
+    }
+
+    #[test]
+    fn step_executes_one_instruction() {
+        let mut machine = machine();
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().reg.a, 3);
+        assert_eq!(machine.cpu().reg.pc, 1);
+
+        machine.apply(Command::Step);
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().reg.a, 4);
+        assert_eq!(machine.cpu().port.output, 0b0101);
+    }
+
+    #[test]
+    fn step_is_ignored_while_running() {
+        let mut machine = machine();
+        machine.apply(Command::Run);
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().reg.pc, 0);
+    }
+
+    #[test]
+    fn tick_only_steps_while_running() {
+        let mut machine = machine();
+        machine.apply(Command::Tick);
+        assert_eq!(machine.cpu().reg.pc, 0);
+
+        machine.apply(Command::Run);
+        machine.apply(Command::Tick);
+        assert_eq!(machine.cpu().reg.pc, 1);
+    }
+
+    #[test]
+    fn breakpoint_stops_run() {
+        let mut machine = machine();
+        machine.apply(Command::ToggleBreakpoint(2));
+        machine.apply(Command::Run);
+        assert_eq!(machine.apply(Command::Tick), None);
+        assert_eq!(machine.apply(Command::Tick), Some(Event::Breakpoint(2)));
+        assert_eq!(machine.state(), RunState::Idle);
+
+        machine.apply(Command::ToggleBreakpoint(2));
+        assert!(!machine.breakpoints()[2]);
+    }
+
+    #[test]
+    fn step_back_restores_previous_state() {
+        let mut machine = machine();
+        machine.apply(Command::Step);
+        machine.apply(Command::Step);
+        machine.apply(Command::StepBack);
+        assert_eq!(machine.cpu().reg.pc, 1);
+        assert_eq!(machine.cpu().reg.a, 3);
+
+        machine.apply(Command::StepBack);
+        assert_eq!(machine.cpu().reg.a, 0);
+        assert_eq!(
+            machine.apply(Command::StepBack),
+            Some(Event::HistoryExhausted)
+        );
+    }
+
+    #[test]
+    fn run_back_stops_at_oldest_state() {
+        let mut machine = machine();
+        machine.apply(Command::Step);
+        machine.apply(Command::RunBack);
+        assert_eq!(machine.apply(Command::Tick), None);
+        assert_eq!(machine.apply(Command::Tick), Some(Event::HistoryExhausted));
+        assert_eq!(machine.state(), RunState::Idle);
+    }
+
+    #[test]
+    fn step_back_keeps_rom_edits_for_undo() {
+        use crate::undo::{Edit, UndoStack};
+
+        let mut machine = machine();
+        let mut undo = UndoStack::default();
+        machine.apply(Command::Step);
+        machine.apply(Command::SetRomByte(5, 0xB3));
+        undo.push(Edit::RomByte {
+            addr: 5,
+            before: 0,
+            after: 0xB3,
+        });
+
+        machine.apply(Command::StepBack);
+        assert_eq!(machine.cpu().reg.pc, 0);
+        assert_eq!(machine.rom()[5], 0xB3);
+
+        match undo.undo().map(|edit| edit.inverse()) {
+            Some(Edit::RomByte { addr, after, .. }) => {
+                machine.apply(Command::SetRomByte(addr, after))
+            }
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(machine.rom()[5], 0);
+        assert_eq!(undo.undo(), None);
+    }
+
+    #[test]
+    fn reset_clears_registers_but_is_recorded() {
+        let mut machine = machine();
+        machine.apply(Command::Step);
+        machine.apply(Command::Reset);
+        assert_eq!(machine.cpu().reg.a, 0);
+        assert_eq!(machine.cpu().reg.pc, 0);
+
+        machine.apply(Command::StepBack);
+        assert_eq!(machine.cpu().reg.a, 3);
+    }
+
+    #[test]
+    fn cycles_count_steps_until_reset() {
+        let mut machine = machine();
+        machine.apply(Command::SetPeriod(200));
+        machine.apply(Command::Step);
+        machine.apply(Command::Step);
+        assert_eq!(machine.cycles(), 2);
+        assert_eq!(machine.elapsed(), 400);
+
+        machine.apply(Command::StepBack);
+        assert_eq!(machine.cycles(), 1);
+
+        machine.apply(Command::Reset);
+        assert_eq!(machine.cycles(), 0);
+        assert_eq!(machine.elapsed(), 0);
+    }
+
+    #[test]
+    fn step_back_across_reset_restores_cycles() {
+        let mut machine = machine();
+        machine.apply(Command::SetPeriod(200));
+        machine.apply(Command::Step);
+        machine.apply(Command::Step);
+        machine.apply(Command::Reset);
+
+        machine.apply(Command::StepBack);
+        assert_eq!(machine.cycles(), 2);
+        assert_eq!(machine.elapsed(), 400);
+        assert_eq!(machine.cpu().reg.pc, 2);
+
+        machine.apply(Command::SetPeriod(500));
+        machine.apply(Command::StepBack);
+        assert_eq!(machine.cycles(), 1);
+        assert_eq!(machine.elapsed(), 200);
+    }
+
+    #[test]
+    fn manual_clock_never_runs() {
+        let mut machine = machine();
+        machine.apply(Command::SetClock(ClockSource::Manual));
+        machine.apply(Command::Run);
+        assert_eq!(machine.state(), RunState::Idle);
+        assert_eq!(machine.interval(), None);
+
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().reg.pc, 1);
+    }
+
+    #[test]
+    fn turbo_runs_a_batch_per_tick() {
+        let mut machine = machine();
+        machine.apply(Command::SetClock(ClockSource::Turbo));
+        machine.apply(Command::SetHaltAction(HaltAction::Ignore));
+        machine.apply(Command::Run);
+        machine.apply(Command::Tick);
+        assert_eq!(machine.cycles(), crate::clock::TURBO_BATCH);
+
+        machine.apply(Command::ToggleBreakpoint(3));
+        assert_eq!(machine.apply(Command::Tick), Some(Event::Breakpoint(3)));
+        assert_eq!(machine.cycles(), crate::clock::TURBO_BATCH + 1);
+    }
+
+    #[test]
+    fn edits_set_individual_bits() {
+        let mut machine = machine();
+        machine.apply(Command::SetInputBit(2, true));
+        assert_eq!(machine.cpu().port.input, 0b0100);
+        machine.apply(Command::SetInputBit(2, false));
+        assert_eq!(machine.cpu().port.input, 0);
+
+        machine.apply(Command::SetRomBit(0, 7, true));
+        assert_eq!(machine.rom()[0], 0b1011_0011);
+    }
+
+    #[test]
+    fn trace_follows_steps_and_rewinds() {
+        let mut machine = machine();
+        machine.apply(Command::Step);
+        machine.apply(Command::SetInput(0b1010));
+        machine.apply(Command::Step);
+        assert_eq!(machine.trace().len(), 3);
+        assert_eq!(machine.trace().get(1).unwrap().input, 0b1010);
+        assert_eq!(machine.trace().get(2).unwrap().a, 4);
+
+        machine.apply(Command::StepBack);
+        assert_eq!(machine.trace().len(), 2);
+        assert_eq!(machine.trace().last().unwrap().cycle, 1);
+
+        machine.apply(Command::Reset);
+        assert_eq!(machine.trace().len(), 1);
+    }
+
+    #[test]
+    fn stimulus_drives_input_in_lockstep() {
+        let mut machine = machine();
+        machine.apply(Command::SetPeriod(100));
+        let stimulus = Stimulus::parse("1:0b0001\n250ms:0x3").unwrap();
+        machine.apply(Command::LoadStimulus(stimulus));
+
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().port.input, 0);
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().port.input, 1);
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().port.input, 1);
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().port.input, 3);
+
+        machine.apply(Command::Reset);
+        machine.apply(Command::Step);
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().port.input, 1);
+    }
+
+    #[test]
+    fn self_jump_is_detected_as_halt() {
+        let mut machine = machine();
+        machine.apply(Command::Run);
+        let events = (0..6)
+            .map(|_| machine.apply(Command::Tick))
+            .collect::<Vec<_>>();
+        assert_eq!(events[..3], [None, None, None]);
+        assert_eq!(events[3], Some(Event::Halted(3)));
+        assert_eq!(machine.state(), RunState::Idle);
+        assert_eq!(machine.cycles(), 4);
+    }
+
+    #[test]
+    fn halt_can_warn_or_be_ignored() {
+        let mut machine = machine();
+        machine.apply(Command::SetHaltAction(HaltAction::Warn));
+        machine.apply(Command::Run);
+        let halts = (0..10)
+            .filter(|_| machine.apply(Command::Tick) == Some(Event::Halted(3)))
+            .count();
+        assert_eq!(halts, 1);
+        assert_eq!(machine.state(), RunState::Active);
+
+        machine.apply(Command::SetHaltAction(HaltAction::Ignore));
+        assert!((0..10).all(|_| machine.apply(Command::Tick).is_none()));
+    }
+
+    #[test]
+    fn input_change_or_pending_stimulus_is_not_a_halt() {
+        let mut machine = machine();
+        machine.apply(Command::LoadStimulus(Stimulus::parse("8:1").unwrap()));
+        for _ in 0..8 {
+            assert_eq!(machine.apply(Command::Step), None);
+        }
+        assert_eq!(machine.cpu().port.input, 0);
+        machine.apply(Command::Step);
+        assert_eq!(machine.cpu().port.input, 1);
+        assert_eq!(machine.apply(Command::Step), Some(Event::Halted(3)));
+
+        assert_eq!(machine.apply(Command::Step), None);
+
+        machine.apply(Command::SetInput(2));
+        assert_eq!(machine.apply(Command::Step), Some(Event::Halted(3)));
+    }
+
+    #[test]
+    fn polling_live_input_is_not_a_halt() {
+        // wait: IN A / ADD A,15 / JNC wait / OUT 1111 / end: JMP end
+        let mut rom = [0; ROM_SIZE];
+        rom[..5].copy_from_slice(&[
+            0b0010_0000,
+            0b0000_1111,
+            0b1110_0000,
+            0b1011_1111,
+            0b1111_0100,
+        ]);
+        let mut machine = Machine::default();
+        machine.apply(Command::LoadRom(rom));
+        machine.apply(Command::Run);
+        assert!((0..20).all(|_| machine.apply(Command::Tick).is_none()));
+        assert_eq!(machine.state(), RunState::Active);
+
+        // Once the button is pressed the program reaches its real end.
+        machine.apply(Command::SetInputBit(0, true));
+        let events = (0..10)
+            .filter_map(|_| machine.apply(Command::Tick))
+            .collect::<Vec<_>>();
+        assert_eq!(events, [Event::Halted(4)]);
+
+        // With the input scripted, waiting forever is a halt again.
+        machine.apply(Command::Reset);
+        machine.apply(Command::LoadStimulus(Stimulus::parse("0:0").unwrap()));
+        machine.apply(Command::Run);
+        assert!((0..20).any(|_| machine.apply(Command::Tick) == Some(Event::Halted(1))));
+    }
+
+    #[test]
+    fn loop_changing_the_output_keeps_running() {
+        // OUT 0001 / OUT 0010 / JMP 0
+        let mut rom = [0; ROM_SIZE];
+        rom[..3].copy_from_slice(&[0b1011_0001, 0b1011_0010, 0b1111_0000]);
+        let mut machine = Machine::default();
+        machine.apply(Command::LoadRom(rom));
+        machine.apply(Command::Run);
+        assert!((0..20).all(|_| machine.apply(Command::Tick).is_none()));
+        assert_eq!(machine.state(), RunState::Active);
+    }
+
+    #[test]
+    fn period_is_clamped() {
+        let mut machine = Machine::default();
+        machine.apply(Command::SetPeriod(5));
+        assert_eq!(machine.period(), MIN_PERIOD);
+        machine.apply(Command::SetPeriod(5000));
+        assert_eq!(machine.period(), MAX_PERIOD);
+    }
+}
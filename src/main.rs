@@ -1,15 +1,22 @@
 extern crate iced;
+extern crate td4;
 extern crate td4_emu;
 
 mod bitbutton;
 mod circle;
+mod cli;
+mod config;
+mod editor;
+mod keymap;
+mod momentary;
+mod segment;
 mod style;
 mod td4_gui;
+mod waveform;
 
 use crate::iced::{Application, Settings};
 
 fn main() -> iced::Result {
-    println!("Hello, world!");
     // let mut emu = td4::Emulator::new("prg.bin");
 
     // println!("{:?}", emu);
@@ -22,14 +29,43 @@ fn main() -> iced::Result {
     //     println!("{:?}", emu);
     // }
 
+    // A settings file that cannot be read is left as it is rather than
+    // overwritten with the defaults.
+    let (config, config_path) = match config::path() {
+        Some(path) => match config::load(&path) {
+            Ok(config) => (config, Some(path)),
+            Err(err) => {
+                eprintln!("warning: ignoring settings {}: {}", path.display(), err);
+                (config::Config::default(), None)
+            }
+        },
+        None => (config::Config::default(), None),
+    };
+
+    let flags = match cli::Flags::parse(config.flags(), std::env::args().skip(1)) {
+        Ok(flags) => flags,
+        Err(cli::CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
     let window = iced::window::Settings {
-        size: (700, 600),
-        resizable: false,
+        size: flags.size,
+        min_size: Some((640, 480)),
+        resizable: true,
         ..iced::window::Settings::default()
     };
 
     let setting = iced::settings::Settings {
         window,
+        flags: (flags, config, config_path),
+        // Closing saves the preferences first; see `Message::CloseRequested`.
+        exit_on_close_request: false,
         ..Settings::default()
     };
 
@@ -0,0 +1,110 @@
+use iced_graphics::{Backend, Defaults, Primitive, Renderer};
+use iced_native::{
+    event, layout, mouse, touch, Clipboard, Element, Event, Hasher, Layout, Length, Point,
+    Rectangle, Widget,
+};
+
+/// Whether the wrapped content is currently held down.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct State {
+    held: bool,
+}
+
+/// Wraps `content` like a push button: `on_press` is produced as soon as the
+/// mouse goes down on it and `on_release` once it comes back up, wherever
+/// the cursor is by then.
+pub struct Momentary<'a, Message, B: Backend> {
+    state: &'a mut State,
+    content: Element<'a, Message, Renderer<B>>,
+    on_press: Message,
+    on_release: Message,
+}
+
+impl<'a, Message, B: Backend> Momentary<'a, Message, B> {
+    pub fn new<E>(state: &'a mut State, content: E, on_press: Message, on_release: Message) -> Self
+    where
+        E: Into<Element<'a, Message, Renderer<B>>>,
+    {
+        Self {
+            state,
+            content: content.into(),
+            on_press,
+            on_release,
+        }
+    }
+}
+
+impl<'a, Message, B> Widget<Message, Renderer<B>> for Momentary<'a, Message, B>
+where
+    Message: Clone,
+    B: Backend,
+{
+    fn width(&self) -> Length {
+        self.content.width()
+    }
+
+    fn height(&self) -> Length {
+        self.content.height()
+    }
+
+    fn layout(&self, renderer: &Renderer<B>, limits: &layout::Limits) -> layout::Node {
+        self.content.layout(renderer, limits)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        _renderer: &Renderer<B>,
+        _clipboard: &mut dyn Clipboard,
+        messages: &mut Vec<Message>,
+    ) -> event::Status {
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+            | Event::Touch(touch::Event::FingerPressed { .. })
+                if layout.bounds().contains(cursor_position) =>
+            {
+                self.state.held = true;
+                messages.push(self.on_press.clone());
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+            | Event::Touch(touch::Event::FingerLifted { .. })
+            | Event::Touch(touch::Event::FingerLost { .. })
+                if self.state.held =>
+            {
+                self.state.held = false;
+                messages.push(self.on_release.clone());
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer<B>,
+        defaults: &Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) -> (Primitive, mouse::Interaction) {
+        self.content
+            .draw(renderer, defaults, layout, cursor_position, viewport)
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        self.content.hash_layout(state);
+    }
+}
+
+impl<'a, Message, B> From<Momentary<'a, Message, B>> for Element<'a, Message, Renderer<B>>
+where
+    Message: 'a + Clone,
+    B: 'a + Backend,
+{
+    fn from(momentary: Momentary<'a, Message, B>) -> Element<'a, Message, Renderer<B>> {
+        Element::new(momentary)
+    }
+}
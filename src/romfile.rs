@@ -0,0 +1,566 @@
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const ROM_SIZE: usize = 16;
+
+#[derive(Debug)]
+pub enum RomFileError {
+    Io(io::Error),
+    Parse { line: usize, text: String },
+    Checksum(usize),
+    Header(String),
+    TooLong(usize),
+}
+
+impl fmt::Display for RomFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomFileError::Io(err) => write!(f, "{}", err),
+            RomFileError::Parse { line, text } => {
+                write!(f, "line {}: \"{}\" is not a valid byte", line, text)
+            }
+            RomFileError::Checksum(line) => write!(f, "line {}: checksum mismatch", line),
+            RomFileError::Header(expected) => write!(f, "missing \"{}\" header", expected),
+            RomFileError::TooLong(len) => {
+                write!(
+                    f,
+                    "{} bytes found, but the ROM holds only {}",
+                    len, ROM_SIZE
+                )
+            }
+        }
+    }
+}
+
+impl From<io::Error> for RomFileError {
+    fn from(err: io::Error) -> Self {
+        RomFileError::Io(err)
+    }
+}
+
+/// Parses the `prg.bin` format: one byte per line written as binary digits.
+/// Blank lines are skipped and missing addresses are filled with zero.
+pub fn parse(text: &str) -> Result<[u8; ROM_SIZE], RomFileError> {
+    let bytes = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let line = line.trim();
+            if line.len() > 8 {
+                return Err(RomFileError::Parse {
+                    line: i + 1,
+                    text: line.to_string(),
+                });
+            }
+            u8::from_str_radix(line, 2).map_err(|_| RomFileError::Parse {
+                line: i + 1,
+                text: line.to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if bytes.len() > ROM_SIZE {
+        return Err(RomFileError::TooLong(bytes.len()));
+    }
+
+    let mut rom = [0; ROM_SIZE];
+    rom[..bytes.len()].copy_from_slice(&bytes);
+    Ok(rom)
+}
+
+pub fn format(rom: &[u8]) -> String {
+    rom.iter()
+        .map(|byte| format!("{:08b}", byte))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// ROM image file formats, chosen by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `prg.bin`: one binary byte per line. Used for unknown extensions.
+    Binary,
+    /// Verilog `$readmemb` (`.memb`).
+    ReadMemB,
+    /// Verilog `$readmemh` (`.memh`).
+    ReadMemH,
+    /// Logisim ROM contents, `v2.0 raw` (`.rom`, `.logisim`).
+    Logisim,
+    /// Intel HEX (`.hex`, `.ihex`).
+    IntelHex,
+    /// C array initializer (`.c`, `.h`).
+    CArray,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Binary => write!(f, "binary"),
+            Format::ReadMemB => write!(f, "Verilog $readmemb"),
+            Format::ReadMemH => write!(f, "Verilog $readmemh"),
+            Format::Logisim => write!(f, "Logisim v2.0 raw"),
+            Format::IntelHex => write!(f, "Intel HEX"),
+            Format::CArray => write!(f, "C array"),
+        }
+    }
+}
+
+impl Format {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Format {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("memb") => Format::ReadMemB,
+            Some("memh") => Format::ReadMemH,
+            Some("rom") | Some("logisim") => Format::Logisim,
+            Some("hex") | Some("ihex") => Format::IntelHex,
+            Some("c") | Some("h") => Format::CArray,
+            _ => Format::Binary,
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<[u8; ROM_SIZE], RomFileError> {
+        match self {
+            Format::Binary => parse(text),
+            Format::ReadMemB => parse_readmem(text, 2),
+            Format::ReadMemH => parse_readmem(text, 16),
+            Format::Logisim => parse_logisim(text),
+            Format::IntelHex => parse_intel_hex(text),
+            Format::CArray => parse_c_array(text),
+        }
+    }
+
+    pub fn format(self, rom: &[u8]) -> String {
+        match self {
+            Format::Binary => format(rom),
+            Format::ReadMemB => format_readmem(rom, |b| format!("{:08b}", b)),
+            Format::ReadMemH => format_readmem(rom, |b| format!("{:02x}", b)),
+            Format::Logisim => format_logisim(rom),
+            Format::IntelHex => format_intel_hex(rom),
+            Format::CArray => format_c_array(rom),
+        }
+    }
+}
+
+/// Collects bytes written at consecutive addresses into a ROM image.
+#[derive(Default)]
+struct Image {
+    rom: [u8; ROM_SIZE],
+    addr: usize,
+}
+
+impl Image {
+    fn push(&mut self, byte: u8) -> Result<(), RomFileError> {
+        if self.addr >= ROM_SIZE {
+            return Err(RomFileError::TooLong(self.addr.saturating_add(1)));
+        }
+        self.rom[self.addr] = byte;
+        self.addr += 1;
+        Ok(())
+    }
+}
+
+/// Removes `//` and `/* */` comments, keeping line breaks so line numbers
+/// still match.
+fn strip_c_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn parse_byte(line: usize, word: &str, radix: u32) -> Result<u8, RomFileError> {
+    u8::from_str_radix(&word.replace('_', ""), radix).map_err(|_| RomFileError::Parse {
+        line,
+        text: word.to_string(),
+    })
+}
+
+fn parse_readmem(text: &str, radix: u32) -> Result<[u8; ROM_SIZE], RomFileError> {
+    let mut image = Image::default();
+    for (i, line) in strip_c_comments(text).lines().enumerate() {
+        for word in line.split_whitespace() {
+            match word.strip_prefix('@') {
+                Some(addr) => {
+                    image.addr = usize::from_str_radix(addr, 16)
+                        .ok()
+                        .filter(|&addr| addr < ROM_SIZE)
+                        .ok_or_else(|| RomFileError::Parse {
+                            line: i + 1,
+                            text: word.to_string(),
+                        })?
+                }
+                None => image.push(parse_byte(i + 1, word, radix)?)?,
+            }
+        }
+    }
+    Ok(image.rom)
+}
+
+fn format_readmem(rom: &[u8], byte: fn(&u8) -> String) -> String {
+    let mut out = String::from("// TD4 program\n");
+    for (addr, b) in rom.iter().enumerate() {
+        let _ = writeln!(out, "{} // {:X}", byte(b), addr);
+    }
+    out
+}
+
+const LOGISIM_HEADER: &str = "v2.0 raw";
+
+fn parse_logisim(text: &str) -> Result<[u8; ROM_SIZE], RomFileError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == LOGISIM_HEADER => {}
+        _ => return Err(RomFileError::Header(LOGISIM_HEADER.to_string())),
+    }
+
+    let mut image = Image::default();
+    for (i, line) in lines {
+        let line = line.split('#').next().unwrap_or("");
+        for word in line.split_whitespace() {
+            let (count, value) = match word.split_once('*') {
+                Some((count, value)) => {
+                    let count = count.parse::<usize>().map_err(|_| RomFileError::Parse {
+                        line: i + 1,
+                        text: word.to_string(),
+                    })?;
+                    (count, value)
+                }
+                None => (1, word),
+            };
+            let byte = parse_byte(i + 1, value, 16)?;
+            for _ in 0..count {
+                image.push(byte)?;
+            }
+        }
+    }
+    Ok(image.rom)
+}
+
+fn format_logisim(rom: &[u8]) -> String {
+    let bytes = rom
+        .iter()
+        .map(|b| format!("{:x}", b))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{}\n{}\n", LOGISIM_HEADER, bytes)
+}
+
+fn parse_intel_hex(text: &str) -> Result<[u8; ROM_SIZE], RomFileError> {
+    let mut rom = [0; ROM_SIZE];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = || RomFileError::Parse {
+            line: i + 1,
+            text: line.to_string(),
+        };
+        let record = line.strip_prefix(':').ok_or_else(invalid)?;
+        if record.len() % 2 != 0 || record.len() < 10 {
+            return Err(invalid());
+        }
+        let bytes = (0..record.len())
+            .step_by(2)
+            .map(|j| u8::from_str_radix(&record[j..j + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(RomFileError::Checksum(i + 1));
+        }
+
+        let len = bytes[0] as usize;
+        if bytes.len() != len + 5 {
+            return Err(invalid());
+        }
+        let addr = ((bytes[1] as usize) << 8) | bytes[2] as usize;
+        let data = &bytes[4..4 + len];
+        // Extended address records would move data past the ROM.
+        match bytes[3] {
+            0x00 => {
+                if addr + len > ROM_SIZE {
+                    return Err(RomFileError::TooLong(addr + len));
+                }
+                rom[addr..addr + len].copy_from_slice(data);
+            }
+            0x01 => break,
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(rom)
+}
+
+fn intel_hex_record(out: &mut String, addr: u16, kind: u8, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, kind];
+    bytes.extend_from_slice(data);
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |sum, b| sum.wrapping_add(*b))
+        .wrapping_neg();
+    out.push(':');
+    for b in bytes.iter().chain(Some(&checksum)) {
+        let _ = write!(out, "{:02X}", b);
+    }
+    out.push('\n');
+}
+
+fn format_intel_hex(rom: &[u8]) -> String {
+    let mut out = String::new();
+    intel_hex_record(&mut out, 0, 0x00, rom);
+    intel_hex_record(&mut out, 0, 0x01, &[]);
+    out
+}
+
+fn parse_c_array(text: &str) -> Result<[u8; ROM_SIZE], RomFileError> {
+    let text = strip_c_comments(text);
+    let start = text
+        .find('{')
+        .ok_or_else(|| RomFileError::Header(String::from("{")))?;
+    let end = text[start..]
+        .find('}')
+        .map(|end| start + end)
+        .ok_or_else(|| RomFileError::Header(String::from("}")))?;
+    let first_line = text[..start].lines().count().max(1);
+
+    let mut image = Image::default();
+    for (i, line) in text[start + 1..end].lines().enumerate() {
+        for word in line.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+            let line = first_line + i;
+            let lower = word.to_ascii_lowercase();
+            let byte = if let Some(hex) = lower.strip_prefix("0x") {
+                parse_byte(line, hex, 16)
+            } else if let Some(bin) = lower.strip_prefix("0b") {
+                parse_byte(line, bin, 2)
+            } else {
+                parse_byte(line, &lower, 10)
+            };
+            image.push(byte.map_err(|_| RomFileError::Parse {
+                line,
+                text: word.to_string(),
+            })?)?;
+        }
+    }
+    Ok(image.rom)
+}
+
+fn format_c_array(rom: &[u8]) -> String {
+    let mut out = format!("const unsigned char td4_rom[{}] = {{\n", rom.len());
+    for chunk in rom.chunks(8) {
+        let bytes = chunk
+            .iter()
+            .map(|b| format!("0x{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(out, "    {},", bytes);
+    }
+    out.push_str("};\n");
+    out
+}
+
+/// Loads a ROM image in the format given by the file extension.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<[u8; ROM_SIZE], RomFileError> {
+    let text = fs::read_to_string(&path)?;
+    Format::from_path(path).parse(&text)
+}
+
+/// Saves a ROM image in the format given by the file extension.
+pub fn save<P: AsRef<Path>>(path: P, rom: &[u8]) -> Result<(), RomFileError> {
+    let text = Format::from_path(&path).format(rom);
+    fs::write(path, text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Format; 6] = [
+        Format::Binary,
+        Format::ReadMemB,
+        Format::ReadMemH,
+        Format::Logisim,
+        Format::IntelHex,
+        Format::CArray,
+    ];
+
+    fn rom() -> [u8; ROM_SIZE] {
+        let mut rom = [0; ROM_SIZE];
+        for (addr, byte) in rom.iter_mut().enumerate() {
+            *byte = (addr as u8).wrapping_mul(0x3B) ^ 0xA5;
+        }
+        rom
+    }
+
+    fn parse_error(format: Format, text: &str) -> RomFileError {
+        match format.parse(text) {
+            Ok(rom) => panic!("{} parsed {:?} from {:?}", format, rom, text),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        for &format in ALL.iter() {
+            let text = format.format(&rom());
+            assert_eq!(
+                format.parse(&text).ok(),
+                Some(rom()),
+                "{}:\n{}",
+                format,
+                text
+            );
+            assert_eq!(
+                format.parse(&format.format(&[0; ROM_SIZE])).ok(),
+                Some([0; ROM_SIZE])
+            );
+        }
+    }
+
+    #[test]
+    fn extension_selects_the_format() {
+        assert_eq!(Format::from_path("prg.bin"), Format::Binary);
+        assert_eq!(Format::from_path("prg.MEMB"), Format::ReadMemB);
+        assert_eq!(Format::from_path("prg.memh"), Format::ReadMemH);
+        assert_eq!(Format::from_path("prg.logisim"), Format::Logisim);
+        assert_eq!(Format::from_path("prg.ihex"), Format::IntelHex);
+        assert_eq!(Format::from_path("prg.h"), Format::CArray);
+        assert_eq!(Format::from_path("prg"), Format::Binary);
+    }
+
+    #[test]
+    fn binary_rejects_bad_lines() {
+        assert!(matches!(
+            parse_error(Format::Binary, "00000001\n\n0000002"),
+            RomFileError::Parse { line: 3, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::Binary, "101010101"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::Binary, &vec!["1"; 17].join("\n")),
+            RomFileError::TooLong(17)
+        ));
+    }
+
+    #[test]
+    fn readmem_rejects_bad_words() {
+        assert!(matches!(
+            parse_error(Format::ReadMemB, "// x\n0000_0001 00000012"),
+            RomFileError::Parse { line: 2, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::ReadMemH, "@g 00"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::ReadMemH, "@f 01 02"),
+            RomFileError::TooLong(17)
+        ));
+        assert!(matches!(
+            parse_error(Format::ReadMemH, "@ffffffffffffffff 00"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::ReadMemB, "@10"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+        assert_eq!(
+            Format::ReadMemH
+                .parse("@2 b3 /* skip */ 0f")
+                .ok()
+                .map(|rom| rom[..4].to_vec()),
+            Some(vec![0, 0, 0xB3, 0x0F])
+        );
+    }
+
+    #[test]
+    fn logisim_rejects_bad_contents() {
+        assert!(matches!(
+            parse_error(Format::Logisim, "01 02"),
+            RomFileError::Header(_)
+        ));
+        assert!(matches!(
+            parse_error(Format::Logisim, "v2.0 raw\n01 zz"),
+            RomFileError::Parse { line: 2, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::Logisim, "v2.0 raw\n17*0"),
+            RomFileError::TooLong(17)
+        ));
+    }
+
+    #[test]
+    fn intel_hex_rejects_bad_records() {
+        assert!(matches!(
+            parse_error(Format::IntelHex, "0100000000FF"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::IntelHex, ":01000000B34D\n:00000001FE"),
+            RomFileError::Checksum(1)
+        ));
+        assert!(matches!(
+            parse_error(Format::IntelHex, ":02000F00B3B389"),
+            RomFileError::TooLong(17)
+        ));
+        // The checksum alone, with the data byte missing.
+        assert!(matches!(
+            parse_error(Format::IntelHex, ":01000000FF"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::IntelHex, ":0100000012B33A"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::IntelHex, ":020000040001F9\n:01000000B34C"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn c_array_rejects_bad_initializers() {
+        assert!(matches!(
+            parse_error(Format::CArray, "0x01, 0x02"),
+            RomFileError::Header(_)
+        ));
+        assert!(matches!(
+            parse_error(Format::CArray, "char rom[] = {\n    0x01,\n    0x1G,\n};"),
+            RomFileError::Parse { line: 3, .. }
+        ));
+        assert!(matches!(
+            parse_error(Format::CArray, "{ 256 }"),
+            RomFileError::Parse { line: 1, .. }
+        ));
+    }
+}
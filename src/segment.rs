@@ -0,0 +1,133 @@
+use iced_graphics::{Backend, Defaults, Primitive, Renderer};
+use iced_native::{
+    layout, mouse, Background, Color, Element, Hasher, Layout, Length, Point, Rectangle, Size,
+    Widget,
+};
+
+/// Segment patterns for hex digits, bit 0 = segment a ... bit 6 = segment g.
+const HEX_FONT: [u8; 16] = [
+    0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F, 0x77, 0x7C, 0x39, 0x5E, 0x79, 0x71,
+];
+
+/// Segments lit for `value` shown as a hex digit.
+pub fn hex(value: u8) -> u8 {
+    HEX_FONT[(value & 0x0F) as usize]
+}
+
+/// Segments lit for `value` fed through a BCD decoder; codes above 9 are
+/// blanked.
+pub fn bcd(value: u8) -> u8 {
+    if value < 10 {
+        HEX_FONT[value as usize]
+    } else {
+        0
+    }
+}
+
+#[derive(Debug)]
+pub struct SevenSegment {
+    height: f32,
+    segments: u8,
+    on: Color,
+    off: Color,
+}
+
+impl SevenSegment {
+    pub fn new(height: f32, segments: u8) -> Self {
+        Self {
+            height,
+            segments,
+            on: Color::from_rgb(1.0, 0.0, 0.0),
+            off: Color::from_rgb(0.2, 0.2, 0.2),
+        }
+    }
+
+    fn width(&self) -> f32 {
+        self.height * 0.6
+    }
+}
+
+impl<Message, B> Widget<Message, Renderer<B>> for SevenSegment
+where
+    B: Backend,
+{
+    fn width(&self) -> Length {
+        Length::Shrink
+    }
+
+    fn height(&self) -> Length {
+        Length::Shrink
+    }
+
+    fn layout(&self, _renderer: &Renderer<B>, _limits: &layout::Limits) -> layout::Node {
+        layout::Node::new(Size::new(SevenSegment::width(self), self.height))
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        use std::hash::Hash;
+
+        self.height.to_bits().hash(state);
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut Renderer<B>,
+        _defaults: &Defaults,
+        layout: Layout<'_>,
+        _cursor_position: Point,
+        _viewport: &Rectangle,
+    ) -> (Primitive, mouse::Interaction) {
+        let Rectangle { x, y, .. } = layout.bounds();
+        let w = SevenSegment::width(self);
+        let h = self.height;
+        let t = h * 0.12;
+        let mid = h / 2.0;
+        let side = mid - t * 1.5;
+
+        // a, b, c, d, e, f, g
+        let rects = [
+            (t, 0.0, w - 2.0 * t, t),
+            (w - t, t, t, side),
+            (w - t, mid + t / 2.0, t, side),
+            (t, h - t, w - 2.0 * t, t),
+            (0.0, mid + t / 2.0, t, side),
+            (0.0, t, t, side),
+            (t, mid - t / 2.0, w - 2.0 * t, t),
+        ];
+
+        let primitives = rects
+            .iter()
+            .enumerate()
+            .map(|(i, &(dx, dy, width, height))| Primitive::Quad {
+                bounds: Rectangle {
+                    x: x + dx,
+                    y: y + dy,
+                    width,
+                    height,
+                },
+                background: Background::Color(if self.segments & (0x01 << i) != 0 {
+                    self.on
+                } else {
+                    self.off
+                }),
+                border_radius: t / 3.0,
+                border_width: 0.0,
+                border_color: Color::TRANSPARENT,
+            })
+            .collect();
+
+        (
+            Primitive::Group { primitives },
+            mouse::Interaction::default(),
+        )
+    }
+}
+
+impl<'a, Message, B> From<SevenSegment> for Element<'a, Message, Renderer<B>>
+where
+    B: Backend,
+{
+    fn from(segment: SevenSegment) -> Element<'a, Message, Renderer<B>> {
+        Element::new(segment)
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// When a stimulus event fires: at a cycle number or at a point in
+/// simulated time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Time {
+    Cycle(u64),
+    Ms(u64),
+}
+
+impl Time {
+    /// Parses a cycle number, or milliseconds with an `ms` suffix.
+    fn parse(text: &str) -> Option<Time> {
+        match text.strip_suffix("ms") {
+            Some(ms) => ms.trim().parse().ok().map(Time::Ms),
+            None => text.parse().ok().map(Time::Cycle),
+        }
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Time::Cycle(cycle) => write!(f, "cycle {}", cycle),
+            Time::Ms(ms) => write!(f, "{} ms", ms),
+        }
+    }
+}
+
+/// Input port values to apply at given cycles or simulated times.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stimulus {
+    events: Vec<(Time, u8)>,
+}
+
+#[derive(Debug)]
+pub enum StimulusError {
+    Io(io::Error),
+    Parse { line: usize, event: String },
+}
+
+impl fmt::Display for StimulusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StimulusError::Io(err) => write!(f, "{}", err),
+            StimulusError::Parse { line, event } => {
+                write!(f, "line {}: invalid stimulus event \"{}\"", line, event)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for StimulusError {
+    fn from(err: io::Error) -> Self {
+        StimulusError::Io(err)
+    }
+}
+
+impl Stimulus {
+    /// Parses comma separated `time:value` pairs, e.g. `0:0b0001,20:3,500ms:0`.
+    pub fn parse_inline(text: &str) -> Result<Stimulus, StimulusError> {
+        let mut stimulus = Stimulus::default();
+        stimulus.parse_line(1, text)?;
+        Ok(stimulus)
+    }
+
+    /// Parses a stimulus file: one or more `time:value` pairs per line,
+    /// where `time` is a cycle number or milliseconds with an `ms` suffix.
+    /// Everything after `#` is a comment.
+    pub fn parse(text: &str) -> Result<Stimulus, StimulusError> {
+        let mut stimulus = Stimulus::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            stimulus.parse_line(i + 1, line)?;
+        }
+        Ok(stimulus)
+    }
+
+    fn parse_line(&mut self, line: usize, text: &str) -> Result<(), StimulusError> {
+        for event in text.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let mut parts = event.splitn(2, ':');
+            let time = parts.next().and_then(|t| Time::parse(t.trim()));
+            let value = parts.next().and_then(|v| parse_nibble(v.trim()));
+            match (time, value) {
+                (Some(time), Some(value)) => self.insert(time, value),
+                _ => {
+                    return Err(StimulusError::Parse {
+                        line,
+                        event: event.to_string(),
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, time: Time, value: u8) {
+        match self.events.binary_search_by_key(&time, |&(t, _)| t) {
+            Ok(i) => self.events[i].1 = value,
+            Err(i) => self.events.insert(i, (time, value)),
+        }
+    }
+
+    /// The value to apply before executing the cycle that starts at
+    /// `cycle`/`elapsed` and lasts `period` milliseconds. A millisecond
+    /// event fires on the first cycle starting at or after its time, and
+    /// never when `period` is zero.
+    pub fn due(&self, cycle: u64, elapsed: u64, period: u64) -> Option<u8> {
+        self.events
+            .iter()
+            .filter(|(time, _)| match *time {
+                Time::Cycle(c) => c == cycle,
+                Time::Ms(ms) => period > 0 && ms <= elapsed && elapsed < ms + period,
+            })
+            .map(|&(_, value)| value)
+            .next_back()
+    }
+
+    /// Events that have not fired yet, soonest first, with the number of
+    /// cycles until each one. Millisecond events never fire when `period`
+    /// is zero and are left out.
+    pub fn upcoming(&self, cycle: u64, elapsed: u64, period: u64) -> Vec<(u64, Time, u8)> {
+        let mut upcoming = self
+            .events
+            .iter()
+            .filter_map(|&(time, value)| {
+                let distance = match time {
+                    Time::Cycle(c) if c >= cycle => c - cycle,
+                    Time::Ms(ms) if period > 0 && ms + period > elapsed => {
+                        ms.saturating_sub(elapsed).div_ceil(period)
+                    }
+                    _ => return None,
+                };
+                Some((distance, time, value))
+            })
+            .collect::<Vec<_>>();
+        upcoming.sort();
+        upcoming
+    }
+
+    pub fn events(&self) -> &[(Time, u8)] {
+        &self.events
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Stimulus, StimulusError> {
+    Stimulus::parse(&fs::read_to_string(path)?)
+}
+
+/// Parses a 4-bit value written in decimal, `0x` hex or `0b` binary.
+pub fn parse_nibble(text: &str) -> Option<u8> {
+    let value = if let Some(bin) = text.strip_prefix("0b") {
+        u8::from_str_radix(bin, 2).ok()?
+    } else if let Some(hex) = text.strip_prefix("0x") {
+        u8::from_str_radix(hex, 16).ok()?
+    } else {
+        text.parse().ok()?
+    };
+    if value < 0x10 {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn millisecond_events_fire_on_the_first_cycle_after_their_time() {
+        let stimulus = Stimulus::parse_inline("2:1,500ms:3").unwrap();
+        assert_eq!(stimulus.due(2, 200, 100), Some(1));
+        assert_eq!(stimulus.due(1, 300, 300), None);
+        assert_eq!(stimulus.due(2, 600, 300), Some(3));
+        assert_eq!(stimulus.due(5, 500, 100), Some(3));
+        assert_eq!(stimulus.due(6, 600, 100), None);
+    }
+
+    #[test]
+    fn millisecond_events_never_fire_without_a_period() {
+        let stimulus = Stimulus::parse_inline("0ms:5,1:2").unwrap();
+        assert_eq!(stimulus.due(0, 0, 0), None);
+        assert_eq!(stimulus.due(1, 0, 0), Some(2));
+        assert_eq!(stimulus.due(0, 0, 300), Some(5));
+    }
+}
@@ -1,5 +1,5 @@
 use iced::{
-    button, checkbox, container, progress_bar, radio, rule, scrollable, slider, text_input,
+    button, checkbox, container, progress_bar, radio, rule, scrollable, slider, text_input, Color,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,6 +18,22 @@ impl Default for Theme {
     }
 }
 
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Theme::Light => write!(f, "Light"),
+            Theme::Dark => write!(f, "Dark"),
+        }
+    }
+}
+
+fn text_color(theme: Theme) -> Color {
+    match theme {
+        Theme::Light => Color::BLACK,
+        Theme::Dark => Color::WHITE,
+    }
+}
+
 impl From<Theme> for Box<dyn container::StyleSheet> {
     fn from(theme: Theme) -> Self {
         match theme {
@@ -99,6 +115,35 @@ impl From<Theme> for Box<dyn rule::StyleSheet> {
     }
 }
 
+/// Borderless, transparent button used to make indicators clickable.
+pub struct Marker(pub Theme);
+
+impl button::StyleSheet for Marker {
+    fn active(&self) -> button::Style {
+        button::Style {
+            background: None,
+            shadow_offset: iced::Vector::new(0.0, 0.0),
+            text_color: text_color(self.0),
+            ..button::Style::default()
+        }
+    }
+}
+
+/// Highlight for selected ROM addresses.
+pub struct Selected(pub Theme);
+
+impl button::StyleSheet for Selected {
+    fn active(&self) -> button::Style {
+        button::Style {
+            background: Color::from_rgba(0.11, 0.42, 0.87, 0.5).into(),
+            border_radius: 4.0,
+            shadow_offset: iced::Vector::new(0.0, 0.0),
+            text_color: text_color(self.0),
+            ..button::Style::default()
+        }
+    }
+}
+
 mod light {
     use iced::{button, Color, Vector};
 
@@ -1,149 +1,988 @@
 use iced::{
-    button, executor, slider, time, Align, Application, Button, Clipboard, Color, Column, Command,
-    Container, Element, Length, Row, Slider, Subscription, Text,
+    button, executor, scrollable, slider, text_input, time, Align, Application, Button, Clipboard,
+    Color, Column, Command, Container, Element, Length, Radio, Row, Scrollable, Slider,
+    Subscription, Text, TextInput,
 };
 
 use super::bitbutton;
 use super::circle;
+use super::cli;
+use super::config::Config;
+use super::editor;
+use super::keymap::{self, Action, KeyMap};
+use super::segment;
 use super::style;
-use td4_emu::emulator::Emulator;
+use super::waveform;
+use iced_native::keyboard::{self, KeyCode, Modifiers};
+use iced_native::{event, subscription, window, Event};
+use std::path::PathBuf;
+use td4::clip::{self, ClipFormat};
+use td4::halt::HaltAction;
+use td4::machine::{self, Machine, RunState};
+use td4::undo::{Edit, UndoStack};
+use td4::{asm, clock, explore, isa, lint, romfile, stimulus, vcd};
 
-#[derive(Debug, Eq, PartialEq)]
-enum State {
-    Idle,
-    Active,
+/// Device attached to the output port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Peripheral {
+    #[default]
+    LedBar,
+    Hex,
+    Decimal,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        State::Idle
+impl Peripheral {
+    pub const ALL: [Peripheral; 3] = [Peripheral::LedBar, Peripheral::Hex, Peripheral::Decimal];
+}
+
+impl std::fmt::Display for Peripheral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Peripheral::LedBar => "LED bar",
+            Peripheral::Hex => "7-seg hex",
+            Peripheral::Decimal => "7-seg BCD",
+        };
+        write!(f, "{}", name)
     }
 }
 
 #[derive(Debug, Default)]
 pub struct TD4 {
     theme: style::Theme,
-    cpu: Emulator,
-    state: State,
+    machine: Machine,
     run: button::State,
     stop: button::State,
     step: button::State,
+    step_back: button::State,
+    run_back: button::State,
     reset: button::State,
     input_state: bitbutton::InputHalfByte,
     output_state: bitbutton::InputHalfByte,
     rom_state: bitbutton::RomTable,
     hoge: bool,
     slider: slider::State,
-    period: u64,
+    path: String,
+    path_input: text_input::State,
+    stimulus_path: String,
+    stimulus_input: text_input::State,
+    file: Option<String>,
+    open: button::State,
+    save: button::State,
+    save_as: button::State,
+    recent: Vec<(String, button::State)>,
+    status: String,
+    editor: editor::SourceEditor,
+    breakpoint_state: [button::State; romfile::ROM_SIZE],
+    rate: clock::RateMeter,
+    peripheral: Peripheral,
+    keymap: KeyMap,
+    held_keys: Vec<KeyCode>,
+    help: bool,
+    help_open: button::State,
+    help_close: button::State,
+    load_stimulus: button::State,
+    clear_stimulus: button::State,
+    waveform: bool,
+    wave_cursor: Option<u64>,
+    wave_toggle: button::State,
+    analysis: Option<explore::Analysis>,
+    analysis_open: bool,
+    analysis_toggle: button::State,
+    wave_latest: button::State,
+    wave_slider: slider::State,
+    export_vcd: button::State,
+    undo: UndoStack,
+    /// ROM contents as last opened or saved, to tell whether there are
+    /// unsaved changes.
+    saved_rom: [u8; romfile::ROM_SIZE],
+    /// Inclusive address range to copy from and paste into; `None` means
+    /// the whole ROM.
+    selection: Option<(usize, usize)>,
+    address_state: [button::State; romfile::ROM_SIZE],
+    clip_format: ClipFormat,
+    copy: button::State,
+    paste: button::State,
+    select_all: button::State,
+    /// One button per lint warning, jumping to its address.
+    lint_state: Vec<button::State>,
+    /// Current window size in pixels.
+    size: (u32, u32),
+    scroll: scrollable::State,
+    /// Preferences chosen in the window; values given on the command line
+    /// are not part of them.
+    config: Config,
+    /// Preferences as last read from or written to the settings file.
+    saved_config: Config,
+    /// Where to save the preferences, `None` if the settings file could not
+    /// be read and must be left alone.
+    config_path: Option<PathBuf>,
+    exit: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+const RECENT_MAX: usize = 5;
+const TIMELINE_LEN: usize = 8;
+/// Window size the layout is designed for; indicators and bit buttons scale
+/// with the window relative to it.
+const BASE_SIZE: (f32, f32) = (1120.0, 720.0);
+/// Width needed at scale 1 to show the panels side by side.
+const WIDE_LAYOUT: f32 = 1100.0;
+const MIN_SCALE: f32 = 0.75;
+const MAX_SCALE: f32 = 2.0;
+/// Color of undefined opcodes and lint warnings.
+const WARNING: Color = Color::from_rgb(0.8, 0.4, 0.0);
+
+/// `value` pixels at `scale`, for widget sizes.
+pub fn scaled(value: f32, scale: f32) -> u16 {
+    (value * scale).round() as u16
+}
+
+#[derive(Debug, Clone)]
 pub enum Message {
     Reset,
     Tick,
     Run,
     Step,
     Stop,
+    StepBack,
+    RunBack,
     RomEdit(usize, u8, bool),
+    RomEntryChanged(usize, String),
+    RomEntrySubmit(usize),
+    TabPressed(bool),
+    WindowResized(u32, u32),
+    CloseRequested,
+    ThemeChanged(style::Theme),
+    SelectAddress(usize),
+    SelectAll,
+    JumpToAddress(usize),
+    ClipFormatChanged(ClipFormat),
+    Copy,
+    Paste,
     InputEdit(u8, bool),
+    InputPress(u8),
+    InputRelease(u8),
+    InputModeToggled(u8),
     SliderChanged(f64),
+    ClockChanged(clock::ClockSource),
+    HaltActionChanged(HaltAction),
+    PeripheralChanged(Peripheral),
+    PathChanged(String),
+    StimulusPathChanged(String),
+    Open,
+    OpenRecent(usize),
+    Save,
+    SaveAs,
+    SourceEdit(usize, String),
+    SourceNewLine(usize),
+    SourceDeleteLine(usize),
+    Assemble,
+    Disassemble,
+    ToggleBreakpoint(usize),
+    KeyPressed(KeyCode, Modifiers),
+    KeyReleased(KeyCode),
+    ToggleHelp,
+    LoadStimulus,
+    ClearStimulus,
+    ToggleWaveform,
+    ToggleAnalysis,
+    WaveCursor(u64),
+    WaveScroll(i64),
+    WaveSlider(f64),
+    WaveLatest,
+    ExportVcd,
 }
 
 impl TD4 {
-    pub fn step(&mut self) {
-        let (opecode, operand) = self.cpu.fetch_decode();
-        // println!("{:?} {:?}", opecode, operand);
-        let next_pc = self.cpu.exec_mut(&opecode, operand);
-        self.cpu.reg.pc = next_pc;
-        // println!("0b_{:04b}", self.cpu.port.output);
+    fn apply(&mut self, command: machine::Command) {
+        match self.machine.apply(command) {
+            Some(machine::Event::Breakpoint(addr)) => {
+                self.status = format!("Stopped at breakpoint on address {}", addr);
+            }
+            Some(machine::Event::HistoryExhausted) => {
+                self.status = String::from("Reached the oldest recorded state");
+            }
+            Some(machine::Event::Halted(addr)) => {
+                self.status = match self.machine.halt_action() {
+                    HaltAction::Warn => {
+                        format!("Program halted at address {} (still running)", addr)
+                    }
+                    _ => format!("Program halted at address {}", addr),
+                };
+            }
+            None => {}
+        }
+    }
+
+    /// Applies the `after` side of `edit` to the machine.
+    fn apply_edit(&mut self, edit: Edit) {
+        match edit {
+            Edit::RomBit { addr, after, .. } | Edit::RomByte { addr, after, .. } => {
+                self.apply(machine::Command::SetRomByte(addr, after));
+            }
+            Edit::Rom { after, .. } => {
+                for (addr, byte) in after.iter().enumerate() {
+                    self.apply(machine::Command::SetRomByte(addr, *byte));
+                }
+            }
+            Edit::Input { after, .. } => self.apply(machine::Command::SetInput(after)),
+        }
+    }
+
+    fn set_input_bit(&mut self, bit: u8, value: bool) {
+        let before = self.machine.cpu().port.input;
+        self.apply(machine::Command::SetInputBit(bit, value));
+        let after = self.machine.cpu().port.input;
+        self.undo.push(Edit::Input { before, after });
+    }
+
+    fn undo(&mut self) {
+        match self.undo.undo() {
+            Some(edit) => {
+                self.apply_edit(edit.inverse());
+                self.status = String::from("Undone");
+            }
+            None => self.status = String::from("Nothing to undo"),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.undo.redo() {
+            Some(edit) => {
+                self.apply_edit(edit);
+                self.status = String::from("Redone");
+            }
+            None => self.status = String::from("Nothing to redo"),
+        }
+    }
+
+    fn select(&mut self, addr: usize) {
+        self.selection = match self.selection {
+            Some((start, end)) if start == end && start != addr => {
+                Some((start.min(addr), start.max(addr)))
+            }
+            _ => Some((addr, addr)),
+        };
+    }
+
+    fn copy(&mut self, clipboard: &mut Clipboard) {
+        let (start, end) = self.selection.unwrap_or((0, romfile::ROM_SIZE - 1));
+        let rom = self.machine.rom();
+        clipboard.write(self.clip_format.format(&rom[start..=end]));
+        self.status = format!("Copied addresses {}-{} as {}", start, end, self.clip_format);
+    }
+
+    fn paste(&mut self, clipboard: &mut Clipboard) {
+        let text = match clipboard.read() {
+            Some(text) => text,
+            None => {
+                self.status = String::from("The clipboard is empty");
+                return;
+            }
+        };
+        let start = self.selection.map_or(0, |(start, _)| start);
+        let bytes = match clip::parse(&text) {
+            Ok(bytes) if start + bytes.len() <= romfile::ROM_SIZE => bytes,
+            Ok(bytes) => {
+                self.status = format!(
+                    "Cannot paste {} bytes at address {}: the ROM ends at {}",
+                    bytes.len(),
+                    start,
+                    romfile::ROM_SIZE - 1
+                );
+                return;
+            }
+            Err(err) => {
+                self.status = format!("Cannot paste: {}", err);
+                return;
+            }
+        };
+
+        let before = self.machine.rom();
+        let mut after = before;
+        after[start..start + bytes.len()].copy_from_slice(&bytes);
+        self.apply_edit(Edit::Rom { before, after });
+        self.undo.push(Edit::Rom { before, after });
+        self.status = format!("Pasted {} bytes at address {}", bytes.len(), start);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.machine.rom() != self.saved_rom
+    }
+
+    fn run_stop(&mut self) {
+        if self.machine.state() == RunState::Idle {
+            self.rate.clear();
+            self.apply(machine::Command::Run);
+            self.status = String::from("Running");
+        } else {
+            self.apply(machine::Command::Stop);
+            self.status = String::from("Stopped");
+        }
+    }
+
+    fn key_pressed(&mut self, key: KeyCode, modifiers: Modifiers) {
+        let typing = self.path_input.is_focused()
+            || self.stimulus_input.is_focused()
+            || self.editor.is_focused()
+            || self.rom_state.focused().is_some();
+        if typing {
+            return;
+        }
+        if modifiers.control {
+            match key {
+                KeyCode::Z if modifiers.shift => self.redo(),
+                KeyCode::Z => self.undo(),
+                KeyCode::Y => self.redo(),
+                _ => {}
+            }
+            return;
+        }
+        if modifiers.alt || modifiers.logo {
+            return;
+        }
+        // Ignore auto-repeat while a key is held down.
+        if self.held_keys.contains(&key) {
+            return;
+        }
+        self.held_keys.push(key);
+
+        match self.keymap.action(key) {
+            Some(Action::ToggleInput(bit)) => {
+                if self.input_state.momentary[bit as usize] {
+                    self.apply(machine::Command::SetInputBit(bit, true));
+                } else {
+                    let value = self.machine.cpu().port.input & (0x01 << bit) == 0;
+                    self.set_input_bit(bit, value);
+                }
+            }
+            Some(Action::Step) => {
+                self.apply(machine::Command::Step);
+                self.show();
+            }
+            Some(Action::RunStop) => self.run_stop(),
+            Some(Action::Reset) => {
+                self.apply(machine::Command::Reset);
+                self.rate.clear();
+            }
+            Some(Action::HoldMode) => {
+                let momentary = !self.input_state.momentary.iter().all(|&m| m);
+                let before = std::mem::replace(&mut self.input_state.momentary, [momentary; 4]);
+                // Latched bits are released, as when toggling a single bit.
+                for (bit, &was) in before.iter().enumerate() {
+                    if momentary && !was {
+                        self.apply(machine::Command::SetInputBit(bit as u8, false));
+                    }
+                }
+                self.status = if momentary {
+                    String::from("Input bits are momentary")
+                } else {
+                    String::from("Input bits are latching")
+                };
+            }
+            Some(Action::Help) => self.help = !self.help,
+            None => {}
+        }
+    }
+
+    fn key_released(&mut self, key: KeyCode) {
+        self.held_keys.retain(|&held| held != key);
+        if let Some(Action::ToggleInput(bit)) = self.keymap.action(key) {
+            if self.input_state.momentary[bit as usize] {
+                self.apply(machine::Command::SetInputBit(bit, false));
+            }
+        }
+    }
+
+    /// The cycle under the waveform cursor, following the latest cycle
+    /// unless one was picked that is still recorded.
+    fn wave_cursor(&self) -> u64 {
+        let trace = self.machine.trace();
+        let last = trace.last().map_or(0, |s| s.cycle);
+        match self.wave_cursor {
+            Some(cycle) if trace.get(cycle).is_some() => cycle,
+            _ => last,
+        }
+    }
+
+    fn move_wave_cursor(&mut self, cycle: u64) {
+        let trace = self.machine.trace();
+        let first = trace.first().map_or(0, |s| s.cycle);
+        let last = trace.last().map_or(0, |s| s.cycle);
+        let cycle = cycle.clamp(first, last);
+        self.wave_cursor = if cycle == last { None } else { Some(cycle) };
+    }
+
+    /// Commits the text typed for `addr`. Returns false if it is invalid.
+    fn commit_rom_entry(&mut self, addr: usize) -> bool {
+        let text = self.rom_state.table[addr].text.trim().to_string();
+        if text.is_empty() {
+            return true;
+        }
+        match asm::parse_byte(&text) {
+            Ok(byte) => {
+                let before = self.machine.rom()[addr];
+                self.apply(machine::Command::SetRomByte(addr, byte));
+                self.undo.push(Edit::RomByte {
+                    addr,
+                    before,
+                    after: byte,
+                });
+                self.rom_state.table[addr].text.clear();
+                self.status = format!("Rom[{}] = 0b{:08b}", addr, byte);
+                true
+            }
+            Err(err) => {
+                self.status = format!("Rom[{}]: {}", addr, err);
+                false
+            }
+        }
     }
 
     pub fn show(&self) {
-        println!("0b{:04b}", self.cpu.port.output);
+        println!("0b{:04b}", self.machine.cpu().port.output);
+    }
+
+    fn open(&mut self, path: String) {
+        match romfile::load(&path) {
+            Ok(rom) => {
+                self.machine.apply(machine::Command::LoadRom(rom));
+                self.undo.clear();
+                self.saved_rom = rom;
+                self.editor.set_source(&asm::disassemble_rom(&rom));
+                self.editor.errors.clear();
+                self.status = format!("Opened {} ({})", path, romfile::Format::from_path(&path));
+                self.remember(&path);
+                self.config.program = Some(path.clone());
+                self.path = path.clone();
+                self.file = Some(path);
+            }
+            Err(err) => {
+                self.status = format!("Cannot open {}: {}", path, err);
+            }
+        }
+    }
+
+    fn load_stimulus(&mut self, path: String) {
+        match stimulus::load(&path) {
+            Ok(stimulus) => {
+                self.status = format!(
+                    "Loaded stimulus {} ({} events)",
+                    path,
+                    stimulus.events().len()
+                );
+                self.apply(machine::Command::LoadStimulus(stimulus));
+            }
+            Err(err) => self.status = format!("Cannot load stimulus {}: {}", path, err),
+        }
+    }
+
+    fn export_vcd(&mut self) {
+        let path = match &self.file {
+            Some(file) => std::path::Path::new(file).with_extension("vcd"),
+            None => {
+                self.status = String::from("Cannot export VCD: open or save a program first");
+                return;
+            }
+        };
+        let trace = self.machine.trace();
+        let period = self
+            .machine
+            .clock()
+            .period(self.machine.period())
+            .unwrap_or(0);
+        self.status = match std::fs::write(&path, vcd::format(trace.iter(), period)) {
+            Ok(()) => format!("Exported {} cycles to {}", trace.len(), path.display()),
+            Err(err) => format!("Cannot write {}: {}", path.display(), err),
+        };
+    }
+
+    fn save(&mut self, path: String) {
+        let rom = self.machine.rom();
+        match romfile::save(&path, &rom) {
+            Ok(()) => {
+                self.saved_rom = rom;
+                self.status = format!("Saved {} ({})", path, romfile::Format::from_path(&path));
+                self.remember(&path);
+                self.config.program = Some(path.clone());
+                self.path = path.clone();
+                self.file = Some(path);
+            }
+            Err(err) => {
+                self.status = format!("Cannot save {}: {}", path, err);
+            }
+        }
+    }
+
+    /// Size factor for indicators and bit buttons at the current window size.
+    fn scale(&self) -> f32 {
+        let (width, height) = self.size;
+        (width as f32 / BASE_SIZE.0)
+            .min(height as f32 / BASE_SIZE.1)
+            .clamp(MIN_SCALE, MAX_SCALE)
+    }
+
+    /// Explores the program again while the analysis is shown and the ROM
+    /// has changed since it was last explored.
+    fn refresh_analysis(&mut self) {
+        if !self.analysis_open {
+            return;
+        }
+        let rom = self.machine.rom();
+        if self
+            .analysis
+            .as_ref()
+            .is_none_or(|analysis| analysis.rom != rom)
+        {
+            self.analysis = Some(explore::analyze(&rom));
+        }
+    }
+
+    /// Writes the preferences if they changed since they were last saved.
+    fn save_config(&mut self) {
+        let path = match &self.config_path {
+            Some(path) if self.config != self.saved_config => path,
+            _ => return,
+        };
+        match self.config.save(path) {
+            Ok(()) => self.saved_config = self.config.clone(),
+            Err(err) => self.status = format!("Cannot save settings {}: {}", path.display(), err),
+        }
+    }
+
+    fn remember(&mut self, path: &str) {
+        self.recent.retain(|(recent, _)| recent != path);
+        self.recent
+            .insert(0, (path.to_string(), button::State::default()));
+        self.recent.truncate(RECENT_MAX);
     }
 }
 
 impl Application for TD4 {
     type Message = Message;
     type Executor = executor::Default;
-    type Flags = ();
+    type Flags = (cli::Flags, Config, Option<PathBuf>);
 
-    fn new(_flags: ()) -> (Self, Command<Message>) {
-        let td4 = TD4 {
-            cpu: Emulator::new("prg.bin"),
-            period: 300,
+    fn new(
+        (flags, config, config_path): (cli::Flags, Config, Option<PathBuf>),
+    ) -> (Self, Command<Message>) {
+        let mut td4 = TD4 {
+            theme: config.theme,
+            keymap: config.keymap.clone(),
+            size: flags.size,
+            config: config.clone(),
+            saved_config: config,
+            config_path,
             ..TD4::default()
         };
+        td4.machine
+            .apply(machine::Command::SetHaltAction(td4.config.halt));
+        td4.open(flags.program);
+        // A program named on the command line is not remembered.
+        td4.config.program = td4.saved_config.program.clone();
+        td4.machine.apply(machine::Command::SetPeriod(flags.period));
+        td4.machine.apply(machine::Command::SetInput(flags.input));
+        if flags.run {
+            td4.machine.apply(machine::Command::Run);
+        }
+        if let Some(path) = flags.stimulus {
+            td4.stimulus_path = path.clone();
+            td4.load_stimulus(path);
+        }
+        if let Some(path) = flags.keymap {
+            match std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| KeyMap::parse(&text))
+            {
+                Ok(keymap) => td4.keymap = keymap,
+                Err(err) => td4.status = format!("Cannot load key bindings {}: {}", path, err),
+            }
+        }
 
         (td4, Command::none())
     }
 
     fn title(&self) -> String {
-        String::from("TD4")
+        let file = self.file.as_deref().unwrap_or("untitled");
+        let dirty = if self.is_dirty() { " *" } else { "" };
+        format!("TD4 - {}{}", file, dirty)
     }
 
-    fn update(&mut self, message: Message, _clipboard: &mut Clipboard) -> Command<Message> {
+    fn update(&mut self, message: Message, clipboard: &mut Clipboard) -> Command<Message> {
         match message {
             Message::Reset => {
-                self.cpu.reg = td4_emu::reg::Reg::default();
-                self.cpu.port = td4_emu::port::Port::default();
+                self.apply(machine::Command::Reset);
+                self.rate.clear();
             }
             Message::Tick => {
-                self.step();
-                self.show();
+                let cycles = self.machine.clock().batch();
+                self.rate.tick(std::time::Instant::now(), cycles);
+                self.apply(machine::Command::Tick);
+                if self.machine.state() == RunState::Active {
+                    self.show();
+                }
             }
             Message::Run => {
-                self.state = State::Active;
+                self.rate.clear();
+                self.apply(machine::Command::Run);
+                self.status = String::from("Running");
             }
 
             Message::Step => {
-                if self.state == State::Idle {
-                    self.step();
-                    self.show();
-                }
+                self.apply(machine::Command::Step);
+                self.show();
             }
             Message::InputEdit(bit, now) => {
-                self.cpu.port.input =
-                    (self.cpu.port.input & !(0x01 << bit)) | ((!now as u8) << bit);
-                println!("Input = 0b{:04b}", self.cpu.port.input);
+                self.set_input_bit(bit, !now);
+                println!("Input = 0b{:04b}", self.machine.cpu().port.input);
+            }
+            Message::InputPress(bit) => {
+                self.apply(machine::Command::SetInputBit(bit, true));
+            }
+            Message::InputRelease(bit) => {
+                self.apply(machine::Command::SetInputBit(bit, false));
+            }
+            Message::InputModeToggled(bit) => {
+                let momentary = &mut self.input_state.momentary[bit as usize];
+                *momentary = !*momentary;
+                if *momentary {
+                    self.apply(machine::Command::SetInputBit(bit, false));
+                }
             }
             Message::RomEdit(addr, bit, now) => {
-                let newbyte = (self.cpu.prg.mem[addr] & !(0x01 << bit)) | ((!now as u8) << bit);
+                let before = self.machine.rom()[addr];
+                self.apply(machine::Command::SetRomBit(addr, bit, !now));
+                let newbyte = self.machine.rom()[addr];
+                self.undo.push(Edit::RomBit {
+                    addr,
+                    before,
+                    after: newbyte,
+                });
                 println!(
                     "Rom[{:2}] = 0b{hi:04b}_{lo:04b}",
                     addr,
                     hi = ((newbyte & 0xF0) >> 4),
                     lo = (newbyte & 0x0F)
                 );
-
-                self.cpu.prg.mem[addr] = newbyte;
+            }
+            Message::RomEntryChanged(addr, text) => {
+                self.rom_state.table[addr].text = text;
+            }
+            Message::RomEntrySubmit(addr) => {
+                self.commit_rom_entry(addr);
+            }
+            Message::TabPressed(backwards) => {
+                if let Some(addr) = self.rom_state.focused() {
+                    if self.commit_rom_entry(addr) {
+                        let len = self.rom_state.table.len();
+                        let next = if backwards { addr + len - 1 } else { addr + 1 } % len;
+                        self.rom_state.focus(next);
+                    }
+                }
+            }
+            Message::SelectAddress(addr) => {
+                self.select(addr);
+            }
+            Message::JumpToAddress(addr) => {
+                self.selection = Some((addr, addr));
+                self.rom_state.focus(addr);
+            }
+            Message::SelectAll => {
+                self.selection = None;
+            }
+            Message::ClipFormatChanged(format) => {
+                self.clip_format = format;
+            }
+            Message::Copy => {
+                self.copy(clipboard);
+            }
+            Message::Paste => {
+                self.paste(clipboard);
+            }
+            Message::StepBack => {
+                self.apply(machine::Command::StepBack);
+            }
+            Message::RunBack => {
+                self.rate.clear();
+                self.apply(machine::Command::RunBack);
+                self.status = String::from("Running backwards");
             }
             Message::Stop => {
-                self.state = State::Idle;
+                self.apply(machine::Command::Stop);
+                self.status = String::from("Stopped");
             }
+            // The period and the window size change continuously while
+            // dragging and are saved with the next other change or on exit.
             Message::SliderChanged(value) => {
-                self.period = value as u64;
+                self.apply(machine::Command::SetPeriod(value as u64));
+                self.config.period = self.machine.period();
+            }
+            Message::HaltActionChanged(halt) => {
+                self.apply(machine::Command::SetHaltAction(halt));
+                self.config.halt = halt;
+                self.save_config();
+            }
+            Message::ThemeChanged(theme) => {
+                self.theme = theme;
+                self.config.theme = theme;
+                self.save_config();
+            }
+            Message::WindowResized(width, height) => {
+                self.size = (width, height);
+                self.config.size = self.size;
+            }
+            Message::CloseRequested => {
+                self.save_config();
+                self.exit = true;
+            }
+            Message::PeripheralChanged(peripheral) => {
+                self.peripheral = peripheral;
+            }
+            Message::ClockChanged(source) => {
+                self.rate.clear();
+                self.apply(machine::Command::SetClock(source));
+            }
+            Message::PathChanged(path) => {
+                self.path = path;
+            }
+            Message::StimulusPathChanged(path) => {
+                self.stimulus_path = path;
+            }
+            Message::Open => {
+                self.open(self.path.clone());
+                self.save_config();
+            }
+            Message::OpenRecent(index) => {
+                if let Some((path, _)) = self.recent.get(index) {
+                    self.open(path.clone());
+                    self.save_config();
+                }
+            }
+            Message::Save => {
+                match self.file.clone() {
+                    Some(path) => self.save(path),
+                    None => self.save(self.path.clone()),
+                }
+                self.save_config();
+            }
+            Message::SaveAs => {
+                self.save(self.path.clone());
+                self.save_config();
+            }
+            Message::SourceEdit(index, text) => {
+                self.editor.edit(index, text);
+            }
+            Message::SourceNewLine(index) => {
+                self.editor.new_line(index);
+            }
+            Message::SourceDeleteLine(index) => {
+                self.editor.delete_line(index);
+            }
+            Message::Assemble => match asm::assemble(&self.editor.source()) {
+                Ok(rom) => {
+                    let before = self.machine.rom();
+                    self.apply(machine::Command::LoadRom(rom));
+                    self.undo.push(Edit::Rom { before, after: rom });
+                    self.editor.errors.clear();
+                    self.status = String::from("Assembled into ROM");
+                }
+                Err(errors) => {
+                    self.editor.errors = errors.iter().map(|err| err.to_string()).collect();
+                    self.status = format!("Assembly failed with {} error(s)", errors.len());
+                }
+            },
+            Message::ToggleBreakpoint(addr) => {
+                self.apply(machine::Command::ToggleBreakpoint(addr));
+            }
+            Message::KeyPressed(key, modifiers) => {
+                self.key_pressed(key, modifiers);
+            }
+            Message::KeyReleased(key) => {
+                self.key_released(key);
+            }
+            Message::ToggleHelp => {
+                self.help = !self.help;
+            }
+            Message::LoadStimulus => {
+                self.load_stimulus(self.stimulus_path.clone());
+            }
+            Message::ToggleAnalysis => {
+                self.analysis_open = !self.analysis_open;
+            }
+            Message::ToggleWaveform => {
+                self.waveform = !self.waveform;
+            }
+            Message::WaveCursor(cycle) => {
+                self.move_wave_cursor(cycle);
+            }
+            Message::WaveScroll(delta) => {
+                let cursor = self.wave_cursor() as i64 + delta;
+                self.move_wave_cursor(cursor.max(0) as u64);
+            }
+            Message::WaveSlider(value) => {
+                self.move_wave_cursor(value as u64);
+            }
+            Message::WaveLatest => {
+                self.wave_cursor = None;
+            }
+            Message::ExportVcd => {
+                self.export_vcd();
+            }
+            Message::ClearStimulus => {
+                self.apply(machine::Command::LoadStimulus(stimulus::Stimulus::default()));
+                self.status = String::from("Stimulus cleared");
+            }
+            Message::Disassemble => {
+                self.editor
+                    .set_source(&asm::disassemble_rom(&self.machine.rom()));
+                self.editor.errors.clear();
             }
         }
 
+        self.refresh_analysis();
         Command::none()
     }
 
+    fn should_exit(&self) -> bool {
+        self.exit
+    }
+
     fn subscription(&self) -> Subscription<Message> {
-        match self.state {
-            State::Idle => Subscription::none(),
-            State::Active => {
-                time::every(std::time::Duration::from_millis(self.period)).map(|_| Message::Tick)
+        let clock = match (self.machine.state(), self.machine.interval()) {
+            (RunState::Idle, _) | (_, None) => Subscription::none(),
+            (RunState::Active, Some(interval)) | (RunState::Reverse, Some(interval)) => {
+                time::every(std::time::Duration::from_millis(interval)).map(|_| Message::Tick)
             }
-        }
+        };
+
+        Subscription::batch(vec![clock, subscription::events_with(window_event)])
     }
 
     fn view(&mut self) -> Element<Message> {
-        let run = Button::new(&mut self.run, Text::new("Run"))
+        if self.help {
+            return help_view(&self.keymap, &mut self.help_close, self.theme);
+        }
+
+        let cpu = self.machine.cpu();
+        let wave_cursor = self.wave_cursor();
+        let scale = self.scale();
+        let wide = self.size.0 as f32 >= WIDE_LAYOUT * scale;
+        let label_width = Length::from(scaled(200.0, scale));
+        let value_width = Length::from(scaled(100.0, scale));
+
+        let path = TextInput::new(
+            &mut self.path_input,
+            "Program file",
+            &self.path,
+            Message::PathChanged,
+        )
+        .padding(10)
+        .on_submit(Message::Open)
+        .style(self.theme);
+
+        let open = Button::new(&mut self.open, Text::new("Open"))
+            .padding(10)
+            .on_press(Message::Open)
+            .style(self.theme);
+
+        let save = Button::new(&mut self.save, Text::new("Save"))
+            .padding(10)
+            .on_press(Message::Save)
+            .style(self.theme);
+
+        let save_as = Button::new(&mut self.save_as, Text::new("Save As"))
             .padding(10)
-            .on_press(Message::Run)
+            .on_press(Message::SaveAs)
             .style(self.theme);
 
+        let stimulus_path = TextInput::new(
+            &mut self.stimulus_input,
+            "Stimulus file",
+            &self.stimulus_path,
+            Message::StimulusPathChanged,
+        )
+        .padding(10)
+        .on_submit(Message::LoadStimulus)
+        .style(self.theme);
+
+        let load_stimulus = Button::new(&mut self.load_stimulus, Text::new("Stimulus"))
+            .padding(10)
+            .on_press(Message::LoadStimulus)
+            .style(self.theme);
+
+        let wave_toggle = Button::new(&mut self.wave_toggle, Text::new("Waveform"))
+            .padding(10)
+            .on_press(Message::ToggleWaveform)
+            .style(self.theme);
+
+        let analysis_toggle = Button::new(&mut self.analysis_toggle, Text::new("Analyze"))
+            .padding(10)
+            .on_press(Message::ToggleAnalysis)
+            .style(self.theme);
+
+        let help = Button::new(&mut self.help_open, Text::new("Keys"))
+            .padding(10)
+            .on_press(Message::ToggleHelp)
+            .style(self.theme);
+
+        let theme = self.theme;
+        let themes =
+            style::Theme::ALL
+                .iter()
+                .fold(Row::new().spacing(10).padding(5), |row, &choice| {
+                    row.push(
+                        Radio::new(
+                            choice,
+                            choice.to_string(),
+                            Some(theme),
+                            Message::ThemeChanged,
+                        )
+                        .size(16)
+                        .text_size(16)
+                        .style(theme),
+                    )
+                });
+
+        let file_controls = Row::new()
+            .spacing(5)
+            .push(path)
+            .push(open)
+            .push(save)
+            .push(save_as)
+            .push(stimulus_path)
+            .push(load_stimulus)
+            .push(wave_toggle)
+            .push(analysis_toggle)
+            .push(help)
+            .push(themes)
+            .align_items(Align::Center);
+
+        let recent = self.recent.iter_mut().enumerate().fold(
+            Row::new().spacing(5).push(Text::new("Recent:").size(16)),
+            |row, (i, (path, state))| {
+                row.push(
+                    Button::new(state, Text::new(path.as_str()).size(16))
+                        .padding(5)
+                        .on_press(Message::OpenRecent(i))
+                        .style(theme),
+                )
+            },
+        );
+
+        let file_info = Column::new()
+            .spacing(5)
+            .push(file_controls)
+            .push(recent.align_items(Align::Center))
+            .push(Text::new(&self.status).size(16));
+
+        let manual = self.machine.clock() == clock::ClockSource::Manual;
+        let run = Button::new(
+            &mut self.run,
+            Text::new(if manual { "Pulse" } else { "Run" }),
+        )
+        .padding(10)
+        .on_press(if manual { Message::Step } else { Message::Run })
+        .style(self.theme);
+
         let step = Button::new(&mut self.step, Text::new("Step"))
             .padding(10)
             .on_press(Message::Step)
@@ -155,122 +994,603 @@ impl Application for TD4 {
             .style(self.theme);
 
         let reset = Button::new(&mut self.reset, Text::new("Reset"))
-            .width(Length::from(300))
+            .width(Length::from(scaled(300.0, scale)))
             .padding(10)
             .on_press(Message::Reset)
             .style(self.theme);
 
+        let mut step_back = Button::new(&mut self.step_back, Text::new("Step Back"))
+            .padding(10)
+            .style(self.theme);
+        let mut run_back = Button::new(&mut self.run_back, Text::new("Run Back"))
+            .padding(10)
+            .style(self.theme);
+        if !self.machine.history().is_empty() {
+            step_back = step_back.on_press(Message::StepBack);
+            run_back = run_back.on_press(Message::RunBack);
+        }
+
         let controls = Row::new().spacing(5).push(run).push(stop).push(step);
+        let back_controls = Row::new()
+            .spacing(5)
+            .push(step_back)
+            .push(run_back)
+            .push(Text::new(format!("{} steps recorded", self.machine.history().len())).size(16))
+            .align_items(Align::Center);
         let slider = Slider::new(
             &mut self.slider,
             100.0..=1000.0,
-            self.period as f64,
+            self.machine.period() as f64,
             Message::SliderChanged,
         );
 
-        let input = self.input_state.crate_layout(&self.cpu.port.input);
+        let measured = match self.rate.hz() {
+            Some(hz) if self.machine.state() != RunState::Idle => format!("{:.2} Hz", hz),
+            _ => String::from("-"),
+        };
+        let source = self.machine.clock();
+        let target = match (source.period(self.machine.period()), source) {
+            (Some(period), _) => format!("{:.2} Hz", clock::period_hz(period)),
+            (None, clock::ClockSource::Turbo) => String::from("max"),
+            (None, _) => String::from("manual"),
+        };
+        let clock_info = Text::new(format!(
+            "Cycles {}  Time {:.1} s  Rate {} / {}",
+            self.machine.cycles(),
+            self.machine.elapsed() as f64 / 1000.0,
+            measured,
+            target,
+        ))
+        .size(16);
+
+        let clock_select =
+            clock::ClockSource::ALL
+                .iter()
+                .fold(Row::new().spacing(10), |row, &choice| {
+                    row.push(
+                        Radio::new(
+                            choice,
+                            choice.to_string(),
+                            Some(source),
+                            Message::ClockChanged,
+                        )
+                        .size(16)
+                        .text_size(16)
+                        .style(theme),
+                    )
+                });
+
+        let halt = self.machine.halt_action();
+        let halt_select = HaltAction::ALL.iter().fold(
+            Row::new().spacing(10).push(Text::new("On halt:").size(16)),
+            |row, &choice| {
+                row.push(
+                    Radio::new(
+                        choice,
+                        choice.to_string(),
+                        Some(halt),
+                        Message::HaltActionChanged,
+                    )
+                    .size(16)
+                    .text_size(16)
+                    .style(theme),
+                )
+            },
+        );
+
+        let input = self.input_state.crate_layout(&cpu.port.input, scale);
         let input_info = Row::new()
             .spacing(20)
-            .push(Text::new("Input Port").width(Length::from(200)))
+            .push(Text::new("Input Port").width(label_width))
             .push(input)
-            .push(Text::new(format!("0x{:1X}", &self.cpu.port.input)).width(Length::from(100)))
+            .push(Text::new(format!("0x{:1X}", &cpu.port.input)).width(value_width))
+            .align_items(Align::End);
+
+        let output = cpu.port.output;
+        let output_display: Element<Message> = match self.peripheral {
+            Peripheral::LedBar => led4bit(&cpu.port.output, scale).into(),
+            Peripheral::Hex => {
+                segment::SevenSegment::new(40.0 * scale, segment::hex(output)).into()
+            }
+            Peripheral::Decimal => {
+                segment::SevenSegment::new(40.0 * scale, segment::bcd(output)).into()
+            }
+        };
+        let output_info = Row::new()
+            .spacing(20)
+            .push(Text::new("Output Port").width(label_width))
+            .push(output_display)
+            .push(Text::new(format!("0x{:1X}", output)).width(value_width))
             .align_items(Align::End);
 
-        let output_info = reg_info(&self.cpu.port.output, "Output Port".to_string());
+        let peripheral = self.peripheral;
+        let peripheral_select =
+            Peripheral::ALL
+                .iter()
+                .fold(Row::new().spacing(10), |row, &choice| {
+                    row.push(
+                        Radio::new(
+                            choice,
+                            choice.to_string(),
+                            Some(peripheral),
+                            Message::PeripheralChanged,
+                        )
+                        .size(16)
+                        .text_size(16)
+                        .style(theme),
+                    )
+                });
 
-        let rega_info = reg_info(&self.cpu.reg.a, "Register A".to_string());
+        let rega_info = reg_info(&cpu.reg.a, "Register A".to_string(), scale);
 
-        let regb_info = reg_info(&self.cpu.reg.b, "Register B".to_string());
+        let regb_info = reg_info(&cpu.reg.b, "Register B".to_string(), scale);
 
-        let pc_info = reg_info(&self.cpu.reg.pc, "Program Counter".to_string());
+        let pc_info = reg_info(&cpu.reg.pc, "Program Counter".to_string(), scale);
 
-        let carry = circle::Circle::new(10.0, bit2color(&self.cpu.reg.flag));
+        let carry = circle::Circle::new(10.0 * scale, bit2color(&cpu.reg.flag));
         let carry_info = Row::new()
             .spacing(20)
-            .push(Text::new("Carry Flag").width(Length::from(200)))
+            .push(Text::new("Carry Flag").width(label_width))
             .push(carry)
-            .push(Text::new(format!("{}", &self.cpu.reg.flag)).width(Length::from(100)))
+            .push(Text::new(format!("{}", &cpu.reg.flag)).width(value_width))
             .align_items(Align::End);
 
         let io = Column::new()
             .spacing(20)
-            .max_width(400)
+            .max_width(scaled(400.0, scale) as u32)
             .push(pc_info)
             .push(rega_info)
             .push(regb_info)
             .push(carry_info)
             .push(output_info)
+            .push(peripheral_select)
             .push(input_info)
+            .push(clock_select)
             .push(slider)
+            .push(clock_info)
+            .push(halt_select)
             .push(controls)
+            .push(back_controls)
             .push(reset)
             .align_items(Align::Center);
 
-        let rom = self.rom_state.create_layout(&self.cpu.prg);
-        let pc = self.cpu.reg.pc;
-        let rom_control =
-            rom.into_iter()
-                .enumerate()
-                .fold(Column::new().spacing(5), |col, (i, btn)| {
+        let rom = self.rom_state.create_layout(&cpu.prg, scale);
+        let pc = cpu.reg.pc;
+        let mem = &cpu.prg.mem;
+        let breakpoints = self.machine.breakpoints();
+        let selection = self.selection;
+        let lints = lint::lint(&self.machine.rom());
+        let mut warned = [false; romfile::ROM_SIZE];
+        for lint in lints.iter() {
+            warned[lint.addr as usize] = true;
+        }
+        let reachable = match &self.analysis {
+            Some(analysis) if self.analysis_open => analysis.reachable,
+            _ => [true; romfile::ROM_SIZE],
+        };
+        let rom_control = rom
+            .into_iter()
+            .zip(self.breakpoint_state.iter_mut())
+            .zip(self.address_state.iter_mut())
+            .enumerate()
+            .fold(
+                Column::new().spacing(5),
+                |col, (i, ((btn, marker), address))| {
+                    let selected = selection.is_some_and(|(start, end)| start <= i && i <= end);
+                    let address = Button::new(address, Text::new(format!("{}:", i)))
+                        .padding(0)
+                        .min_width(30)
+                        .on_press(Message::SelectAddress(i));
+                    let address = if selected {
+                        address.style(style::Selected(theme))
+                    } else {
+                        address.style(style::Marker(theme))
+                    };
                     col.push(
                         Row::new()
                             .spacing(10)
-                            .push(Text::new(format!("{}:", i)))
+                            .push(address)
                             .push(btn)
+                            .push(mnemonic(mem[i], scale, !reachable[i]))
+                            .push(
+                                Text::new(if warned[i] { "!" } else { "" })
+                                    .size(scaled(18.0, scale))
+                                    .width(Length::from(scaled(12.0, scale)))
+                                    .color(WARNING),
+                            )
                             .push(
-                                Container::new(circle::Circle::new(
-                                    10.0,
-                                    if pc == i as u8 {
-                                        Color::from_rgb(1.0, 0.0, 0.0)
-                                    } else {
-                                        Color::BLACK
-                                    },
-                                ))
-                                .center_x()
-                                .center_y(),
+                                Button::new(
+                                    marker,
+                                    Container::new(circle::Circle::new(
+                                        10.0 * scale,
+                                        pc2color(pc == i as u8, breakpoints[i]),
+                                    ))
+                                    .center_x()
+                                    .center_y(),
+                                )
+                                .padding(0)
+                                .on_press(Message::ToggleBreakpoint(i))
+                                .style(style::Marker(theme)),
                             ),
                     )
                     .align_items(Align::End)
-                });
+                },
+            );
 
-        let content = Row::new()
-            .spacing(20)
-            .push(io)
-            .push(rom_control)
+        let clip_format = self.clip_format;
+        let clip_controls = ClipFormat::ALL.iter().fold(
+            Row::new().spacing(10).align_items(Align::Center),
+            |row, &choice| {
+                row.push(
+                    Radio::new(
+                        choice,
+                        choice.to_string(),
+                        Some(clip_format),
+                        Message::ClipFormatChanged,
+                    )
+                    .size(16)
+                    .text_size(16)
+                    .style(theme),
+                )
+            },
+        );
+        let clip_controls = clip_controls
+            .push(
+                Button::new(&mut self.copy, Text::new("Copy").size(16))
+                    .padding(5)
+                    .on_press(Message::Copy)
+                    .style(theme),
+            )
+            .push(
+                Button::new(&mut self.paste, Text::new("Paste").size(16))
+                    .padding(5)
+                    .on_press(Message::Paste)
+                    .style(theme),
+            );
+        let selection_info = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                Text::new(match selection {
+                    Some((start, end)) => format!("Addresses {}-{} selected", start, end),
+                    None => String::from("Whole ROM selected"),
+                })
+                .size(16),
+            )
+            .push(
+                Button::new(&mut self.select_all, Text::new("All").size(16))
+                    .padding(5)
+                    .on_press(Message::SelectAll)
+                    .style(theme),
+            );
+        self.lint_state
+            .resize_with(lints.len(), button::State::default);
+        let lint_list = lints.iter().zip(self.lint_state.iter_mut()).fold(
+            Column::new().spacing(2),
+            |col, (lint, state)| {
+                col.push(
+                    Button::new(state, Text::new(lint.to_string()).size(14).color(WARNING))
+                        .padding(0)
+                        .on_press(Message::JumpToAddress(lint.addr as usize))
+                        .style(style::Marker(theme)),
+                )
+            },
+        );
+        let rom_control = rom_control
+            .push(clip_controls)
+            .push(selection_info)
+            .push(lint_list);
+
+        // Side by side on wide windows, stacked on narrow ones.
+        let editor = self.editor.create_layout(self.theme, scale);
+        let machine: Element<Message> = if wide {
+            Row::new()
+                .spacing(20)
+                .push(io)
+                .push(rom_control)
+                .push(editor)
+                .align_items(Align::Center)
+                .into()
+        } else {
+            Column::new()
+                .spacing(20)
+                .push(io)
+                .push(rom_control)
+                .push(editor)
+                .align_items(Align::Center)
+                .into()
+        };
+
+        let period = self
+            .machine
+            .clock()
+            .period(self.machine.period())
+            .unwrap_or(0);
+        let upcoming =
+            self.machine
+                .stimulus()
+                .upcoming(self.machine.cycles(), self.machine.elapsed(), period);
+        let timeline = upcoming.iter().take(TIMELINE_LEN).fold(
+            Row::new()
+                .spacing(15)
+                .push(Text::new("Stimulus").width(Length::from(120))),
+            |row, (distance, time, value)| {
+                row.push(
+                    Column::new()
+                        .push(Text::new(format!("+{}", distance)).size(14))
+                        .push(Text::new(format!("{:04b}", value)))
+                        .push(Text::new(time.to_string()).size(12))
+                        .align_items(Align::Center),
+                )
+            },
+        );
+        let timeline = timeline
+            .push(
+                Text::new(if upcoming.is_empty() {
+                    String::from("no more events")
+                } else {
+                    format!("{} upcoming", upcoming.len())
+                })
+                .size(14),
+            )
+            .push(
+                Button::new(&mut self.clear_stimulus, Text::new("Clear").size(14))
+                    .padding(5)
+                    .on_press(Message::ClearStimulus)
+                    .style(self.theme),
+            )
             .align_items(Align::Center);
 
+        let mut content = Column::new()
+            .spacing(20)
+            .padding(10)
+            .push(file_info)
+            .push(machine);
+
+        if self.waveform {
+            let trace = self.machine.trace();
+            let first = trace.first().map_or(0, |s| s.cycle);
+            let last = trace.last().map_or(0, |s| s.cycle);
+            let state = match trace.get(wave_cursor) {
+                Some(s) => format!(
+                    "Cycle {}  PC {}  A {:X}  B {:X}  C {}  IN {:04b}  OUT {:04b}",
+                    s.cycle, s.pc, s.a, s.b, s.carry as u8, s.input, s.output
+                ),
+                None => String::from("Nothing recorded"),
+            };
+            let mut header = Row::new()
+                .spacing(20)
+                .push(Text::new("Waveform"))
+                .push(Text::new(state).size(16).width(Length::Fill))
+                .push(
+                    Button::new(&mut self.export_vcd, Text::new("Export VCD").size(14))
+                        .padding(5)
+                        .on_press(Message::ExportVcd)
+                        .style(theme),
+                )
+                .align_items(Align::Center);
+            if self.wave_cursor.is_some() {
+                header = header.push(
+                    Button::new(&mut self.wave_latest, Text::new("Latest").size(14))
+                        .padding(5)
+                        .on_press(Message::WaveLatest)
+                        .style(theme),
+                );
+            }
+            let mut panel = Column::new().spacing(5).push(header);
+            if first < last {
+                panel = panel.push(
+                    Slider::new(
+                        &mut self.wave_slider,
+                        first as f64..=last as f64,
+                        wave_cursor as f64,
+                        Message::WaveSlider,
+                    )
+                    .style(theme),
+                );
+            }
+            content = content.push(panel.push(waveform::Waveform::new(trace, wave_cursor)));
+        }
+        if !self.machine.stimulus().is_empty() {
+            content = content.push(timeline);
+        }
+        if let (true, Some(analysis)) = (self.analysis_open, &self.analysis) {
+            content = content.push(analysis_view(analysis));
+        }
+
+        let content = Scrollable::new(&mut self.scroll)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .push(Container::new(content).width(Length::Fill).center_x())
+            .style(self.theme);
+
         Container::new(content)
             .width(Length::Fill)
             .height(Length::Fill)
             .center_x()
             .center_y()
+            .style(self.theme)
             .into()
     }
 }
 
-pub fn led4bit(halfbyte: &u8) -> Row<Message> {
+fn window_event(event: Event, status: event::Status) -> Option<Message> {
+    match (event, status) {
+        (Event::Window(window::Event::Resized { width, height }), _) => {
+            Some(Message::WindowResized(width, height))
+        }
+        (Event::Window(window::Event::CloseRequested), _) => Some(Message::CloseRequested),
+        (
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code,
+                modifiers,
+            }),
+            event::Status::Ignored,
+        ) => Some(Message::KeyPressed(key_code, modifiers)),
+        // Text inputs capture Tab, but it moves between ROM rows.
+        (
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: KeyCode::Tab,
+                modifiers,
+            }),
+            event::Status::Captured,
+        ) => Some(Message::TabPressed(modifiers.shift)),
+        (Event::Keyboard(keyboard::Event::KeyReleased { key_code, .. }), _) => {
+            Some(Message::KeyReleased(key_code))
+        }
+        _ => None,
+    }
+}
+
+fn help_view<'a>(
+    keymap: &KeyMap,
+    close: &'a mut button::State,
+    theme: style::Theme,
+) -> Element<'a, Message> {
+    let fixed = [
+        ("Ctrl+Z", "Undo ROM or input edit"),
+        ("Ctrl+Y", "Redo"),
+        ("Tab", "Next ROM address while typing a byte"),
+    ];
+    let bindings = keymap
+        .bindings()
+        .iter()
+        .map(|(key, action)| (keymap::key_name(*key), action.to_string()))
+        .chain(
+            fixed
+                .iter()
+                .map(|(key, action)| (key.to_string(), action.to_string())),
+        )
+        .fold(Column::new().spacing(10), |col, (key, action)| {
+            col.push(
+                Row::new()
+                    .spacing(20)
+                    .push(Text::new(key).width(Length::from(120)))
+                    .push(Text::new(action)),
+            )
+        });
+
+    let content = Column::new()
+        .spacing(20)
+        .max_width(500)
+        .push(Text::new("Keyboard").size(30))
+        .push(bindings)
+        .push(
+            Button::new(close, Text::new("Close"))
+                .padding(10)
+                .on_press(Message::ToggleHelp)
+                .style(theme),
+        );
+
+    Container::new(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .style(theme)
+        .into()
+}
+
+fn analysis_view<'a>(analysis: &explore::Analysis) -> Column<'a, Message> {
+    let join = |values: &[u8]| {
+        values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let unreachable = analysis.unreachable();
+    let summary = format!(
+        "{} reachable states, {} transitions. Unreachable addresses: {}",
+        analysis.states,
+        analysis.transitions,
+        if unreachable.is_empty() {
+            String::from("none")
+        } else {
+            join(&unreachable)
+        }
+    );
+
+    let loops = analysis.loops.iter().fold(
+        Column::new()
+            .spacing(2)
+            .push(Text::new("Terminal loops").size(16)),
+        |col, terminal| {
+            let outputs = terminal
+                .outputs
+                .iter()
+                .map(|v| format!("{:X}", v))
+                .collect::<Vec<_>>()
+                .join(" ");
+            col.push(
+                Text::new(format!(
+                    "Addresses {}: {} states, outputs {}",
+                    join(&terminal.addresses),
+                    terminal.states,
+                    outputs
+                ))
+                .size(14),
+            )
+        },
+    );
+    let runs = analysis.runs.iter().fold(
+        Column::new()
+            .spacing(2)
+            .push(Text::new("Runs with a constant input").size(16)),
+        |col, run| col.push(Text::new(run.to_string()).size(14)),
+    );
+
+    Column::new()
+        .spacing(10)
+        .push(Text::new("State space"))
+        .push(Text::new(summary).size(16))
+        .push(loops)
+        .push(runs)
+}
+
+pub fn led4bit(halfbyte: &u8, scale: f32) -> Row<Message> {
     (0..4)
         .into_iter()
         .rev()
         .fold(Row::new().spacing(1), |row, i| {
             row.push(circle::Circle::new(
-                10.0,
+                10.0 * scale,
                 bit2color(&((halfbyte & (0x01 << i)) != 0)),
             ))
         })
 }
 
-pub fn reg_info(reg: &u8, text: String) -> Row<Message> {
-    let led = led4bit(reg);
+pub fn reg_info(reg: &u8, text: String, scale: f32) -> Row<Message> {
+    let led = led4bit(reg, scale);
     let info = Row::new()
         .spacing(20)
-        .push(Text::new(&text).width(Length::from(200)))
+        .push(Text::new(&text).width(Length::from(scaled(200.0, scale))))
         .push(led)
-        .push(Text::new(format!("0x{:1X}", reg)).width(Length::from(100)))
+        .push(Text::new(format!("0x{:1X}", reg)).width(Length::from(scaled(100.0, scale))))
         .align_items(Align::End);
     info
 }
 
+/// Disassembly of a ROM row; `dead` greys out code no input can reach.
+fn mnemonic(byte: u8, scale: f32, dead: bool) -> Text {
+    match isa::disassemble(byte) {
+        Some(text) if dead => Text::new(text).color(Color::from_rgb(0.5, 0.5, 0.5)),
+        Some(text) => Text::new(text),
+        None => Text::new(format!("?? {:04b}", byte >> 4)).color(WARNING),
+    }
+    .size(scaled(18.0, scale))
+    .width(Length::from(scaled(90.0, scale)))
+}
+
+fn pc2color(pc: bool, breakpoint: bool) -> Color {
+    match (pc, breakpoint) {
+        (true, true) => Color::from_rgb(1.0, 0.0, 1.0),
+        (true, false) => Color::from_rgb(1.0, 0.0, 0.0),
+        (false, true) => Color::from_rgb(0.0, 0.4, 1.0),
+        (false, false) => Color::BLACK,
+    }
+}
+
 fn bit2color(bit: &bool) -> Color {
     if *bit {
         Color::from_rgb(1.0, 0.0, 0.0)
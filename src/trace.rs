@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+
+use td4_emu::emulator::Emulator;
+
+pub const TRACE_DEPTH: usize = 4096;
+
+/// Registers and ports as they were at the start of a cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub cycle: u64,
+    pub pc: u8,
+    pub a: u8,
+    pub b: u8,
+    pub carry: bool,
+    pub input: u8,
+    pub output: u8,
+}
+
+impl Sample {
+    pub fn capture(cycle: u64, cpu: &Emulator) -> Self {
+        Sample {
+            cycle,
+            pc: cpu.reg.pc,
+            a: cpu.reg.a,
+            b: cpu.reg.b,
+            carry: cpu.reg.flag,
+            input: cpu.port.input,
+            output: cpu.port.output,
+        }
+    }
+}
+
+/// Every recorded cycle in order, for timing diagrams and trace export.
+/// The oldest samples are dropped once `capacity` is reached.
+#[derive(Debug)]
+pub struct Trace {
+    samples: VecDeque<Sample>,
+    capacity: usize,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Trace::new(TRACE_DEPTH)
+    }
+}
+
+impl Trace {
+    pub fn new(capacity: usize) -> Self {
+        Trace {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records `sample`, replacing the last one if it is for the same cycle
+    /// (e.g. after the input port was changed between steps).
+    pub fn record(&mut self, sample: Sample) {
+        match self.samples.back_mut() {
+            Some(last) if last.cycle == sample.cycle => *last = sample,
+            _ => {
+                if self.samples.len() == self.capacity {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back(sample);
+            }
+        }
+    }
+
+    /// Drops samples after `cycle`, used when stepping backwards.
+    pub fn truncate(&mut self, cycle: u64) {
+        while self.samples.back().is_some_and(|s| s.cycle > cycle) {
+            self.samples.pop_back();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// The sample recorded for `cycle`, if it is still in the trace.
+    pub fn get(&self, cycle: u64) -> Option<&Sample> {
+        let first = self.samples.front()?.cycle;
+        self.samples
+            .get(cycle.checked_sub(first)? as usize)
+            .filter(|s| s.cycle == cycle)
+    }
+
+    pub fn first(&self) -> Option<&Sample> {
+        self.samples.front()
+    }
+
+    pub fn last(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
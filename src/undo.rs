@@ -0,0 +1,229 @@
+use std::collections::VecDeque;
+
+use super::romfile::ROM_SIZE;
+
+/// Number of edits that can be undone; older ones are forgotten.
+pub const UNDO_DEPTH: usize = 256;
+
+/// A reversible change made by the user, with the values before and after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// A single ROM bit toggled; consecutive flips of one byte are grouped.
+    RomBit {
+        addr: usize,
+        before: u8,
+        after: u8,
+    },
+    /// A whole ROM byte typed in.
+    RomByte {
+        addr: usize,
+        before: u8,
+        after: u8,
+    },
+    /// The whole ROM replaced, e.g. by assembling.
+    Rom {
+        before: [u8; ROM_SIZE],
+        after: [u8; ROM_SIZE],
+    },
+    Input {
+        before: u8,
+        after: u8,
+    },
+}
+
+impl Edit {
+    fn is_noop(&self) -> bool {
+        match self {
+            Edit::RomBit { before, after, .. }
+            | Edit::RomByte { before, after, .. }
+            | Edit::Input { before, after } => before == after,
+            Edit::Rom { before, after } => before == after,
+        }
+    }
+
+    /// The edit that reverts this one.
+    pub fn inverse(&self) -> Edit {
+        match *self {
+            Edit::RomBit {
+                addr,
+                before,
+                after,
+            } => Edit::RomBit {
+                addr,
+                before: after,
+                after: before,
+            },
+            Edit::RomByte {
+                addr,
+                before,
+                after,
+            } => Edit::RomByte {
+                addr,
+                before: after,
+                after: before,
+            },
+            Edit::Rom { before, after } => Edit::Rom {
+                before: after,
+                after: before,
+            },
+            Edit::Input { before, after } => Edit::Input {
+                before: after,
+                after: before,
+            },
+        }
+    }
+}
+
+/// Undo and redo stacks of [`Edit`]s, keeping the latest [`UNDO_DEPTH`].
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    /// Whether the next bit flip may join the latest edit.
+    grouping: bool,
+}
+
+impl UndoStack {
+    /// Records an edit that has just been applied and forgets anything that
+    /// could be redone.
+    pub fn push(&mut self, edit: Edit) {
+        self.redo.clear();
+        let grouping = std::mem::replace(&mut self.grouping, false);
+        let edit = match (self.undo.back(), edit) {
+            (
+                Some(&Edit::RomBit {
+                    addr: last_addr,
+                    before,
+                    ..
+                }),
+                Edit::RomBit { addr, after, .. },
+            ) if grouping && last_addr == addr => {
+                self.undo.pop_back();
+                Edit::RomBit {
+                    addr,
+                    before,
+                    after,
+                }
+            }
+            _ => edit,
+        };
+        if let Edit::RomBit { .. } = edit {
+            self.grouping = true;
+        }
+        if !edit.is_noop() {
+            self.record(edit);
+        }
+    }
+
+    fn record(&mut self, edit: Edit) {
+        if self.undo.len() == UNDO_DEPTH {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(edit);
+    }
+
+    /// Takes the latest edit; apply its [`Edit::inverse`] to revert it.
+    pub fn undo(&mut self) -> Option<Edit> {
+        let edit = self.undo.pop_back()?;
+        self.grouping = false;
+        self.redo.push(edit);
+        Some(edit)
+    }
+
+    /// Takes the latest undone edit so it can be applied again.
+    pub fn redo(&mut self) -> Option<Edit> {
+        let edit = self.redo.pop()?;
+        self.grouping = false;
+        self.record(edit);
+        Some(edit)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.grouping = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(before: u8, after: u8) -> Edit {
+        Edit::Input { before, after }
+    }
+
+    fn bit(addr: usize, before: u8, after: u8) -> Edit {
+        Edit::RomBit {
+            addr,
+            before,
+            after,
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_go_in_opposite_order() {
+        let mut stack = UndoStack::default();
+        stack.push(input(0, 1));
+        stack.push(input(1, 2));
+        stack.push(input(2, 3));
+
+        assert_eq!(stack.undo(), Some(input(2, 3)));
+        assert_eq!(stack.undo(), Some(input(1, 2)));
+        assert_eq!(stack.redo(), Some(input(1, 2)));
+        assert_eq!(stack.redo(), Some(input(2, 3)));
+        assert_eq!(stack.redo(), None);
+        assert_eq!(stack.undo(), Some(input(2, 3)));
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut stack = UndoStack::default();
+        stack.push(input(0, 1));
+        stack.push(input(1, 2));
+        stack.undo();
+        stack.push(input(1, 5));
+
+        assert_eq!(stack.redo(), None);
+        assert_eq!(stack.undo(), Some(input(1, 5)));
+        assert_eq!(stack.undo(), Some(input(0, 1)));
+        assert_eq!(stack.undo(), None);
+    }
+
+    #[test]
+    fn bit_flips_of_one_byte_are_grouped() {
+        let mut stack = UndoStack::default();
+        stack.push(bit(3, 0b0000, 0b0001));
+        stack.push(bit(3, 0b0001, 0b0011));
+        stack.push(bit(4, 0b0000, 0b1000));
+        stack.push(bit(4, 0b1000, 0b0000));
+
+        assert_eq!(stack.undo(), Some(bit(3, 0b0000, 0b0011)));
+        assert_eq!(stack.undo(), None);
+
+        stack.redo();
+        stack.push(bit(3, 0b0011, 0b0111));
+        assert_eq!(stack.undo(), Some(bit(3, 0b0011, 0b0111)));
+    }
+
+    #[test]
+    fn oldest_edits_are_dropped_past_the_depth() {
+        let mut stack = UndoStack::default();
+        // The address only tells the edits apart here.
+        for addr in 0..=UNDO_DEPTH {
+            stack.push(Edit::RomByte {
+                addr,
+                before: 0,
+                after: 1,
+            });
+        }
+        let mut oldest = None;
+        let mut undone = 0;
+        while let Some(Edit::RomByte { addr, .. }) = stack.undo() {
+            oldest = Some(addr);
+            undone += 1;
+        }
+        assert_eq!(undone, UNDO_DEPTH);
+        assert_eq!(oldest, Some(1));
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::Write;
+
+use super::trace::Sample;
+
+/// Signals in dump order: name, width, identifier code.
+const SIGNALS: [(&str, u8, char); 7] = [
+    ("clk", 1, '!'),
+    ("pc", 4, '"'),
+    ("a", 4, '#'),
+    ("b", 4, '$'),
+    ("carry", 1, '%'),
+    ("in", 4, '&'),
+    ("out", 4, '\''),
+];
+
+fn values(sample: &Sample) -> [u8; 6] {
+    [
+        sample.pc,
+        sample.a,
+        sample.b,
+        sample.carry as u8,
+        sample.input,
+        sample.output,
+    ]
+}
+
+fn value(out: &mut String, width: u8, id: char, value: u8) {
+    if width == 1 {
+        let _ = writeln!(out, "{}{}", value & 0x01, id);
+    } else {
+        let _ = writeln!(out, "b{:04b} {}", value, id);
+    }
+}
+
+/// Formats `samples` as a Value Change Dump. Each sample starts with a
+/// rising clock edge and the clock falls half a `period` (in milliseconds)
+/// later; with no period, e.g. for the manual clock, each cycle takes 1 ms.
+pub fn format<'a, I>(samples: I, period: u64) -> String
+where
+    I: IntoIterator<Item = &'a Sample>,
+{
+    let period = period.max(1) * 1000;
+    let mut out = String::new();
+    out.push_str("$version td4-gui $end\n$timescale 1us $end\n$scope module td4 $end\n");
+    for (name, width, id) in SIGNALS.iter() {
+        let _ = writeln!(out, "$var wire {} {} {} $end", width, id, name);
+    }
+    out.push_str("$upscope $end\n$enddefinitions $end\n");
+
+    let mut previous: Option<[u8; 6]> = None;
+    let mut end = 0;
+    for sample in samples {
+        let time = sample.cycle * period;
+        let current = values(sample);
+        let _ = writeln!(out, "#{}", time);
+        if previous.is_none() {
+            out.push_str("$dumpvars\n");
+        }
+        value(&mut out, 1, SIGNALS[0].2, 1);
+        for (i, &(_, width, id)) in SIGNALS[1..].iter().enumerate() {
+            if previous.is_none_or(|p| p[i] != current[i]) {
+                value(&mut out, width, id, current[i]);
+            }
+        }
+        if previous.is_none() {
+            out.push_str("$end\n");
+        }
+        let _ = writeln!(out, "#{}", time + period / 2);
+        value(&mut out, 1, SIGNALS[0].2, 0);
+
+        previous = Some(current);
+        end = time + period;
+    }
+    if previous.is_some() {
+        let _ = writeln!(out, "#{}", end);
+    }
+    out
+}
@@ -0,0 +1,259 @@
+use iced_graphics::{Backend, Defaults, Primitive, Renderer};
+use iced_native::{
+    event, layout, mouse, Background, Clipboard, Color, Element, Event, Font, Hasher,
+    HorizontalAlignment, Layout, Length, Point, Rectangle, Size, VerticalAlignment, Widget,
+};
+use td4::trace::{Sample, Trace};
+
+use super::td4_gui::Message;
+
+const LABEL_WIDTH: f32 = 50.0;
+const CELL_WIDTH: f32 = 16.0;
+const ROW_HEIGHT: f32 = 18.0;
+const LINE: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy)]
+enum Signal {
+    Bus(&'static str, fn(&Sample) -> u8),
+    Bit(&'static str, fn(&Sample) -> bool),
+}
+
+const SIGNALS: [Signal; 14] = [
+    Signal::Bus("PC", |s| s.pc),
+    Signal::Bus("A", |s| s.a),
+    Signal::Bus("B", |s| s.b),
+    Signal::Bit("C", |s| s.carry),
+    Signal::Bus("IN", |s| s.input),
+    Signal::Bit("IN3", |s| s.input & 0x08 != 0),
+    Signal::Bit("IN2", |s| s.input & 0x04 != 0),
+    Signal::Bit("IN1", |s| s.input & 0x02 != 0),
+    Signal::Bit("IN0", |s| s.input & 0x01 != 0),
+    Signal::Bus("OUT", |s| s.output),
+    Signal::Bit("OUT3", |s| s.output & 0x08 != 0),
+    Signal::Bit("OUT2", |s| s.output & 0x04 != 0),
+    Signal::Bit("OUT1", |s| s.output & 0x02 != 0),
+    Signal::Bit("OUT0", |s| s.output & 0x01 != 0),
+];
+
+/// Timing diagram of the recorded trace: one row per bus and per bit, one
+/// column per cycle. Clicking moves the cursor to a cycle and the mouse
+/// wheel scrolls it; the visible window keeps the cursor in the middle.
+pub struct Waveform<'a> {
+    trace: &'a Trace,
+    cursor: u64,
+}
+
+impl<'a> Waveform<'a> {
+    pub fn new(trace: &'a Trace, cursor: u64) -> Self {
+        Self { trace, cursor }
+    }
+
+    /// First cycle shown when `width` pixels are available.
+    fn start(&self, width: f32) -> u64 {
+        let (first, last) = match (self.trace.first(), self.trace.last()) {
+            (Some(first), Some(last)) => (first.cycle, last.cycle),
+            _ => return 0,
+        };
+        let visible = ((width - LABEL_WIDTH) / CELL_WIDTH).max(1.0) as u64;
+        let latest_start = last.saturating_sub(visible - 1).max(first);
+        self.cursor
+            .saturating_sub(visible / 2)
+            .clamp(first, latest_start)
+    }
+
+    fn cycle_at(&self, bounds: Rectangle, x: f32) -> Option<u64> {
+        if x < bounds.x + LABEL_WIDTH {
+            return None;
+        }
+        let cycle = self.start(bounds.width) + ((x - bounds.x - LABEL_WIDTH) / CELL_WIDTH) as u64;
+        self.trace.get(cycle).map(|s| s.cycle)
+    }
+}
+
+fn quad(x: f32, y: f32, width: f32, height: f32, color: Color) -> Primitive {
+    Primitive::Quad {
+        bounds: Rectangle {
+            x,
+            y,
+            width,
+            height,
+        },
+        background: Background::Color(color),
+        border_radius: 0.0,
+        border_width: 0.0,
+        border_color: Color::TRANSPARENT,
+    }
+}
+
+fn label(content: String, x: f32, y: f32, size: f32, color: Color) -> Primitive {
+    Primitive::Text {
+        content,
+        bounds: Rectangle {
+            x,
+            y,
+            width: LABEL_WIDTH,
+            height: ROW_HEIGHT,
+        },
+        color,
+        size,
+        font: Font::Default,
+        horizontal_alignment: HorizontalAlignment::Left,
+        vertical_alignment: VerticalAlignment::Center,
+    }
+}
+
+impl<'a, B> Widget<Message, Renderer<B>> for Waveform<'a>
+where
+    B: Backend,
+{
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Shrink
+    }
+
+    fn layout(&self, _renderer: &Renderer<B>, limits: &layout::Limits) -> layout::Node {
+        let height = ROW_HEIGHT * SIGNALS.len() as f32;
+        let size = limits.width(Length::Fill).resolve(Size::new(0.0, height));
+        layout::Node::new(Size::new(size.width, height))
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        use std::hash::Hash;
+
+        SIGNALS.len().hash(state);
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        _renderer: &Renderer<B>,
+        _clipboard: &mut dyn Clipboard,
+        messages: &mut Vec<Message>,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        if !bounds.contains(cursor_position) {
+            return event::Status::Ignored;
+        }
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                if let Some(cycle) = self.cycle_at(bounds, cursor_position.x) {
+                    messages.push(Message::WaveCursor(cycle));
+                    return event::Status::Captured;
+                }
+            }
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { x, y } => x - y,
+                    mouse::ScrollDelta::Pixels { x, y } => (x - y) / CELL_WIDTH,
+                };
+                let step = lines.round() as i64;
+                if step != 0 {
+                    messages.push(Message::WaveScroll(step));
+                    return event::Status::Captured;
+                }
+            }
+            _ => {}
+        }
+        event::Status::Ignored
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut Renderer<B>,
+        _defaults: &Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        _viewport: &Rectangle,
+    ) -> (Primitive, mouse::Interaction) {
+        let bounds = layout.bounds();
+        let background = Color::from_rgb(0.1, 0.1, 0.1);
+        let text = Color::from_rgb(0.8, 0.8, 0.8);
+        let wave = Color::from_rgb(0.2, 0.9, 0.3);
+        let marker = Color::from_rgb(1.0, 0.8, 0.0);
+
+        let start = self.start(bounds.width);
+        let visible = ((bounds.width - LABEL_WIDTH) / CELL_WIDTH).max(1.0) as u64;
+        let samples = (start..start + visible)
+            .filter_map(|cycle| self.trace.get(cycle))
+            .collect::<Vec<_>>();
+
+        let mut primitives = vec![quad(
+            bounds.x,
+            bounds.y,
+            bounds.width,
+            bounds.height,
+            background,
+        )];
+
+        if self.cursor >= start && self.cursor < start + visible {
+            let x = bounds.x + LABEL_WIDTH + (self.cursor - start) as f32 * CELL_WIDTH;
+            primitives.push(quad(
+                x,
+                bounds.y,
+                CELL_WIDTH,
+                bounds.height,
+                Color { a: 0.25, ..marker },
+            ));
+        }
+
+        for (row, signal) in SIGNALS.iter().enumerate() {
+            let top = bounds.y + row as f32 * ROW_HEIGHT;
+            let high = top + 3.0;
+            let low = top + ROW_HEIGHT - 3.0 - LINE;
+            let name = match signal {
+                Signal::Bus(name, _) | Signal::Bit(name, _) => *name,
+            };
+            primitives.push(label(name.to_string(), bounds.x + 4.0, top, 14.0, text));
+
+            for (i, sample) in samples.iter().enumerate() {
+                let x = bounds.x + LABEL_WIDTH + i as f32 * CELL_WIDTH;
+                let previous = i.checked_sub(1).map(|p| samples[p]);
+                match signal {
+                    Signal::Bit(_, bit) => {
+                        let y = if bit(sample) { high } else { low };
+                        primitives.push(quad(x, y, CELL_WIDTH, LINE, wave));
+                        if previous.is_some_and(|p| bit(p) != bit(sample)) {
+                            primitives.push(quad(x, high, LINE, low - high + LINE, wave));
+                        }
+                    }
+                    Signal::Bus(_, bus) => {
+                        primitives.push(quad(x, high, CELL_WIDTH, LINE, wave));
+                        primitives.push(quad(x, low, CELL_WIDTH, LINE, wave));
+                        if previous.is_none_or(|p| bus(p) != bus(sample)) {
+                            primitives.push(quad(x, high, LINE, low - high + LINE, wave));
+                            primitives.push(label(
+                                format!("{:X}", bus(sample)),
+                                x + 3.0,
+                                top,
+                                12.0,
+                                text,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        let interaction = if bounds.contains(cursor_position) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        };
+
+        (Primitive::Group { primitives }, interaction)
+    }
+}
+
+impl<'a, B> From<Waveform<'a>> for Element<'a, Message, Renderer<B>>
+where
+    B: 'a + Backend,
+{
+    fn from(waveform: Waveform<'a>) -> Element<'a, Message, Renderer<B>> {
+        Element::new(waveform)
+    }
+}